@@ -0,0 +1,223 @@
+use std::fmt::Write;
+
+use vecmath::{vec2_len, vec2_sub};
+
+use types::{State, Event, Scalar, GameMode};
+
+/// What it takes to unlock an achievement
+#[derive(Debug, Clone, PartialEq)]
+pub enum Goal {
+    /// Catch the given amount of prey in one game while obstacles are invisible
+    CatchWhileInvisible(u32),
+    /// Stay alive for the given amount of seconds, where obstacles are deadly and
+    /// the game is no practice
+    Survive(f64),
+    /// Reach the given score multiplier
+    ReachMultiplier(Scalar),
+    /// Have the given amount of obstacles within reach of the hunter's force while
+    /// it repels them
+    RepelAtOnce(usize),
+    /// Catch the given amount of prey in a row
+    Combo(u32),
+    /// Clear the last wave of the campaign
+    CompleteCampaign,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Achievement {
+    /// The name used in files
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub goal: Goal,
+}
+
+const ACHIEVEMENTS: &'static [Achievement] = &[Achievement {
+                                                    id: "unseen",
+                                                    name: "Unseen",
+                                                    description: "Catch 10 prey in one game \
+                                                                  while obstacles are invisible",
+                                                    goal: Goal::CatchWhileInvisible(10),
+                                                },
+                                                Achievement {
+                                                    id: "survivor",
+                                                    name: "Survivor",
+                                                    description: "Survive for 2 minutes",
+                                                    goal: Goal::Survive(120.0),
+                                                },
+                                                Achievement {
+                                                    id: "speedster",
+                                                    name: "Speedster",
+                                                    description: "Reach a multiplier of 3.0",
+                                                    goal: Goal::ReachMultiplier(3.0),
+                                                },
+                                                Achievement {
+                                                    id: "crowd_control",
+                                                    name: "Crowd Control",
+                                                    description: "Repel 5 obstacles at once",
+                                                    goal: Goal::RepelAtOnce(5),
+                                                },
+                                                Achievement {
+                                                    id: "combo_breaker",
+                                                    name: "Combo Breaker",
+                                                    description: "Catch 5 prey in a row",
+                                                    goal: Goal::Combo(5),
+                                                },
+                                                Achievement {
+                                                    id: "campaigner",
+                                                    name: "Campaigner",
+                                                    description: "Complete the campaign",
+                                                    goal: Goal::CompleteCampaign,
+                                                }];
+
+impl Achievement {
+    pub fn all() -> &'static [Achievement] {
+        ACHIEVEMENTS
+    }
+
+    pub fn from_id(id: &str) -> Option<&'static Achievement> {
+        ACHIEVEMENTS.iter().find(|a| a.id == id)
+    }
+}
+
+/// Remembers unlocked achievements, and checks games for reached goals
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Achievements {
+    unlocked: Vec<&'static Achievement>,
+    /// Prey caught in the current game while obstacles were invisible
+    invisible_catches: u32,
+}
+
+impl Achievements {
+    /// Parses the format written by `to_text()`, ignoring unknown achievements
+    pub fn parse(content: &str) -> Achievements {
+        let mut achievements = Achievements::default();
+        for achievement in content.lines().filter_map(|l| Achievement::from_id(l.trim())) {
+            if !achievements.is_unlocked(achievement) {
+                achievements.unlocked.push(achievement);
+            }
+        }
+        achievements
+    }
+
+    /// The ids of all unlocked achievements, one per line
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for achievement in &self.unlocked {
+            writeln!(out, "{}", achievement.id).unwrap();
+        }
+        out
+    }
+
+    pub fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.unlocked.iter().any(|a| a.id == achievement.id)
+    }
+
+    /// All unlocked achievements, in the order they were unlocked
+    pub fn unlocked(&self) -> &[&'static Achievement] {
+        &self.unlocked
+    }
+
+    /// Forget the progress made in the previous game
+    pub fn start_game(&mut self) {
+        self.invisible_catches = 0;
+    }
+
+    /// To be called after every update of the engine, with its events and the state
+    /// after the update, and whether the game is practice, like `Engine::is_practice()`.
+    /// Returns the achievements unlocked by it.
+    pub fn observe(&mut self,
+                   events: &[Event],
+                   s: &State,
+                   practice: bool)
+                   -> Vec<&'static Achievement> {
+        let invisible = !s.obstacle_opacity.is_pristine();
+        let mut campaign_complete = false;
+        for event in events {
+            match *event {
+                Event::PreyCaught { .. } if invisible => self.invisible_catches += 1,
+                Event::CampaignComplete => campaign_complete = true,
+                _ => {}
+            }
+        }
+
+        let mut unlocked = Vec::new();
+        for achievement in ACHIEVEMENTS {
+            let reached = match achievement.goal {
+                Goal::CatchWhileInvisible(count) => self.invisible_catches >= count,
+                Goal::Survive(seconds) => {
+                    !practice && s.mode != GameMode::Zen && s.time >= seconds
+                }
+                Goal::ReachMultiplier(multiplier) => s.score_coeff >= multiplier,
+                Goal::RepelAtOnce(count) => s.hunter.force > 0.0 && repelled(s) >= count,
+                Goal::Combo(count) => s.combo >= count,
+                Goal::CompleteCampaign => campaign_complete,
+            };
+            if reached && !self.is_unlocked(achievement) {
+                self.unlocked.push(achievement);
+                unlocked.push(achievement);
+            }
+        }
+        unlocked
+    }
+}
+
+/// The amount of obstacles within reach of the hunter's force
+fn repelled(s: &State) -> usize {
+    let radius = s.hunter.force_radius();
+    s.obstacles
+        .iter()
+        .filter(|o| vec2_len(vec2_sub(o.object.pos, s.hunter.object.pos)) <= radius)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::Engine;
+    use types::Event;
+
+    #[test]
+    fn unlock_and_persist() {
+        let mut s = Engine::from_seed([800.0, 600.0], 1).state().clone().unwrap();
+        let mut achievements = Achievements::default();
+        assert!(achievements.observe(&[], &s, false).is_empty());
+
+        // Surviving doesn't count without deadly obstacles, or in practice
+        s.time = 121.0;
+        s.mode = GameMode::Zen;
+        assert!(achievements.observe(&[], &s, false).is_empty());
+        s.mode = GameMode::Classic;
+        assert!(achievements.observe(&[], &s, true).is_empty());
+
+        s.score_coeff = 3.5;
+        let unlocked = achievements.observe(&[], &s, false);
+        assert_eq!(unlocked.iter().map(|a| a.id).collect::<Vec<_>>(),
+                   vec!["survivor", "speedster"]);
+        assert!(achievements.observe(&[], &s, false).is_empty());
+
+        // Catches only count while obstacles are invisible
+        let catch = Event::PreyCaught {
+            pos: [0.0, 0.0],
+            score: 10,
+        };
+        let unseen = Achievement::from_id("unseen").unwrap();
+        for _ in 0..10 {
+            achievements.observe(&[catch.clone()], &s, false);
+        }
+        assert!(!achievements.is_unlocked(unseen));
+        s.obstacle_opacity.advance(0.1);
+        for _ in 0..9 {
+            achievements.observe(&[catch.clone()], &s, false);
+        }
+        achievements.start_game();
+        assert!(achievements.observe(&[catch.clone()], &s, false).is_empty());
+        for _ in 0..9 {
+            achievements.observe(&[catch.clone()], &s, false);
+        }
+        assert!(achievements.is_unlocked(unseen));
+
+        let restored = Achievements::parse(&format!("{}unknown\n", achievements.to_text()));
+        assert_eq!(restored.unlocked(), achievements.unlocked());
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+
+use image::{Image, png_header, png_data, push_chunk, push_u32, crc32};
+
+const GIF_MAX_CODE_SIZE: u8 = 12;
+/// Entries of the 6x7x6 colour cube frames fall back to if they have too many colours
+const CUBE_LEVELS: [u32; 3] = [6, 7, 6];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    /// Animated PNG, which keeps all colours exactly
+    Apng,
+}
+
+impl AnimationFormat {
+    /// The format matching a file extension like `gif` or `png`
+    pub fn from_extension(ext: &str) -> Option<AnimationFormat> {
+        match &ext.to_lowercase()[..] {
+            "gif" => Some(AnimationFormat::Gif),
+            "png" | "apng" => Some(AnimationFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes images of the same size into an animation which loops forever.
+///
+/// Frames are encoded as they are pushed, so only the encoded animation is kept in memory.
+pub struct Animation {
+    format: AnimationFormat,
+    width: u32,
+    height: u32,
+    out: Vec<u8>,
+    num_frames: u32,
+    /// Sequence number of the next APNG chunk
+    sequence: u32,
+    /// Where the APNG frame count goes, which is only known when finishing
+    actl_pos: usize,
+}
+
+impl Animation {
+    pub fn new(format: AnimationFormat, width: u32, height: u32) -> Animation {
+        let mut out = Vec::new();
+        let mut actl_pos = 0;
+        match format {
+            AnimationFormat::Gif => {
+                assert!(width <= 0xffff && height <= 0xffff,
+                        "GIF animations are at most 65535 pixels wide and high");
+                out.extend_from_slice(b"GIF89a");
+                push_u16_le(&mut out, width as u16);
+                push_u16_le(&mut out, height as u16);
+                // No global colour table, background colour, aspect ratio
+                out.extend_from_slice(&[0, 0, 0]);
+                // Loop forever
+                out.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\0\0\0");
+            }
+            AnimationFormat::Apng => {
+                out = png_header(width, height);
+                actl_pos = out.len();
+                // Frame count and number of plays, where 0 means forever
+                push_chunk(&mut out, b"acTL", &[0; 8]);
+            }
+        }
+        Animation {
+            format: format,
+            width: width,
+            height: height,
+            out: out,
+            num_frames: 0,
+            sequence: 0,
+            actl_pos: actl_pos,
+        }
+    }
+
+    /// Append `img`, which is shown for `delay` seconds
+    pub fn push(&mut self, img: &Image, delay: f64) {
+        assert!(img.width == self.width && img.height == self.height,
+                "All frames must have the size of the animation");
+        match self.format {
+            AnimationFormat::Gif => self.push_gif(img, delay),
+            AnimationFormat::Apng => self.push_apng(img, delay),
+        }
+        self.num_frames += 1;
+    }
+
+    fn push_gif(&mut self, img: &Image, delay: f64) {
+        let (palette, indices) = palettize(img);
+        let bits = (1..8).find(|&b| 1usize << b >= palette.len()).unwrap_or(8) as u8;
+        let out = &mut self.out;
+
+        // Graphic control extension with the delay in hundredths of a second
+        out.extend_from_slice(&[0x21, 0xf9, 4, 0]);
+        push_u16_le(out, (delay * 100.0).round().max(1.0).min(65535.0) as u16);
+        out.extend_from_slice(&[0, 0]);
+
+        // Image descriptor, followed by the local colour table
+        out.push(0x2c);
+        push_u16_le(out, 0);
+        push_u16_le(out, 0);
+        push_u16_le(out, self.width as u16);
+        push_u16_le(out, self.height as u16);
+        out.push(0x80 | (bits - 1));
+        for cid in 0..1 << bits {
+            out.extend_from_slice(&palette.get(cid).cloned().unwrap_or([0; 3]));
+        }
+
+        let min_code_size = bits.max(2);
+        out.push(min_code_size);
+        for block in lzw(&indices, min_code_size).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+
+    fn push_apng(&mut self, img: &Image, delay: f64) {
+        let mut control = Vec::with_capacity(26);
+        push_u32(&mut control, self.sequence);
+        push_u32(&mut control, self.width);
+        push_u32(&mut control, self.height);
+        push_u32(&mut control, 0);
+        push_u32(&mut control, 0);
+        // Delay in thousandths of a second
+        push_u16_be(&mut control, (delay * 1000.0).round().min(65535.0) as u16);
+        push_u16_be(&mut control, 1000);
+        // Neither dispose nor blend, every frame replaces the previous one
+        control.extend_from_slice(&[0, 0]);
+        push_chunk(&mut self.out, b"fcTL", &control);
+        self.sequence += 1;
+
+        if self.num_frames == 0 {
+            push_chunk(&mut self.out, b"IDAT", &png_data(img));
+        } else {
+            let mut data = Vec::new();
+            push_u32(&mut data, self.sequence);
+            data.extend(png_data(img));
+            push_chunk(&mut self.out, b"fdAT", &data);
+            self.sequence += 1;
+        }
+    }
+
+    /// Returns the encoded animation
+    pub fn finish(mut self) -> Vec<u8> {
+        match self.format {
+            AnimationFormat::Gif => self.out.push(0x3b),
+            AnimationFormat::Apng => {
+                let mut actl = Vec::new();
+                push_u32(&mut actl, self.num_frames);
+                push_u32(&mut actl, 0);
+                let data_pos = self.actl_pos + 8;
+                self.out[data_pos..data_pos + 8].copy_from_slice(&actl);
+                let crc = crc32(&self.out[self.actl_pos + 4..data_pos + 8]);
+                let mut crc_bytes = Vec::new();
+                push_u32(&mut crc_bytes, crc);
+                self.out[data_pos + 8..data_pos + 12].copy_from_slice(&crc_bytes);
+                push_chunk(&mut self.out, b"IEND", &[]);
+            }
+        }
+        self.out
+    }
+}
+
+fn push_u16_le(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&[v as u8, (v >> 8) as u8]);
+}
+
+fn push_u16_be(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&[(v >> 8) as u8, v as u8]);
+}
+
+/// Returns the colours of `img`, and the index into them for each pixel.
+/// Images with more than 256 colours are reduced to a colour cube.
+fn palettize(img: &Image) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let mut palette = Vec::new();
+    let mut lookup = HashMap::new();
+    let mut indices = Vec::with_capacity(img.pixels.len() / 4);
+    for p in img.pixels.chunks(4) {
+        let color = [p[0], p[1], p[2]];
+        let index = *lookup.entry(color).or_insert_with(|| {
+            palette.push(color);
+            palette.len() - 1
+        });
+        if index > 255 {
+            return cube_palettize(img);
+        }
+        indices.push(index as u8);
+    }
+    (palette, indices)
+}
+
+fn cube_palettize(img: &Image) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let level = |v: u8, c: usize| v as u32 * CUBE_LEVELS[c] / 256;
+    let value = |l: u32, c: usize| (l * 255 / (CUBE_LEVELS[c] - 1)) as u8;
+    let mut palette = Vec::new();
+    for r in 0..CUBE_LEVELS[0] {
+        for g in 0..CUBE_LEVELS[1] {
+            for b in 0..CUBE_LEVELS[2] {
+                palette.push([value(r, 0), value(g, 1), value(b, 2)]);
+            }
+        }
+    }
+    let indices = img.pixels
+        .chunks(4)
+        .map(|p| {
+            ((level(p[0], 0) * CUBE_LEVELS[1] + level(p[1], 1)) * CUBE_LEVELS[2] +
+             level(p[2], 2)) as u8
+        })
+        .collect();
+    (palette, indices)
+}
+
+/// Writes codes of variable size, starting with the least significant bit
+struct CodeWriter {
+    out: Vec<u8>,
+    bits: u32,
+    num_bits: u8,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.bits |= (code as u32) << self.num_bits;
+        self.num_bits += size;
+        while self.num_bits >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.num_bits -= 8;
+        }
+    }
+}
+
+/// Compresses palette indices as GIF expects them
+fn lzw(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut w = CodeWriter {
+        out: Vec::new(),
+        bits: 0,
+        num_bits: 0,
+    };
+    let mut dict: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut size = min_code_size + 1;
+    let mut next = end + 1;
+
+    w.write(clear, size);
+    let mut prefix = match indices.first() {
+        Some(&i) => i as u16,
+        None => {
+            w.write(end, size);
+            return w.out;
+        }
+    };
+    for &index in &indices[1..] {
+        if let Some(&code) = dict.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        w.write(prefix, size);
+        if next == 1 << GIF_MAX_CODE_SIZE {
+            // The table is full, so start over
+            w.write(clear, size);
+            dict.clear();
+            size = min_code_size + 1;
+            next = end + 1;
+        } else {
+            // The decoder learns about new codes one step later, and grows the
+            // code size when its table is full
+            if next >= 1 << size && size < GIF_MAX_CODE_SIZE {
+                size += 1;
+            }
+            dict.insert((prefix, index), next);
+            next += 1;
+        }
+        prefix = index as u16;
+    }
+    w.write(prefix, size);
+    if next >= 1 << size && size < GIF_MAX_CODE_SIZE {
+        size += 1;
+    }
+    w.write(end, size);
+    if w.num_bits > 0 {
+        w.out.push(w.bits as u8);
+    }
+    w.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Image;
+
+    fn frames() -> Vec<Image> {
+        (0..3)
+            .map(|fid| {
+                let mut img = Image::new(64, 48);
+                img.fill([1.0, 0.8, 0.0, 1.0]);
+                for x in 0..64 {
+                    for y in 0..48 {
+                        if (x + y * 3 + fid * 7) % 11 == 0 {
+                            img.blend(x, y, [x as f32 / 64.0, y as f32 / 48.0, 0.5, 1.0]);
+                        }
+                    }
+                }
+                img
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encode() {
+        for &(format, magic) in &[(AnimationFormat::Gif, &b"GIF89a"[..]),
+                                  (AnimationFormat::Apng, &b"\x89PNG"[..])] {
+            let mut anim = Animation::new(format, 64, 48);
+            for img in &frames() {
+                anim.push(img, 0.05);
+            }
+            let data = anim.finish();
+            assert_eq!(&data[..magic.len()], magic);
+        }
+        assert_eq!(AnimationFormat::from_extension("GIF"), Some(AnimationFormat::Gif));
+        assert_eq!(AnimationFormat::from_extension("bmp"), None);
+    }
+
+    /// Reads codes of variable size, starting with the least significant bit
+    struct CodeReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> CodeReader<'a> {
+        fn read(&mut self, size: u8) -> u16 {
+            let mut code = 0;
+            for bit in 0..size {
+                let byte = self.data[self.pos / 8];
+                code |= (((byte >> (self.pos % 8)) & 1) as u16) << bit;
+                self.pos += 1;
+            }
+            code
+        }
+    }
+
+    fn unlzw(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1u16 << min_code_size;
+        let end = clear + 1;
+        let mut r = CodeReader { data: data, pos: 0 };
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut size = min_code_size + 1;
+        let mut prev: Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        loop {
+            let code = r.read(size);
+            if code == clear {
+                table = (0..end + 1).map(|i| vec![i as u8]).collect();
+                size = min_code_size + 1;
+                prev = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = match table.get(code as usize) {
+                Some(entry) => entry.clone(),
+                None => {
+                    assert_eq!(code as usize, table.len(), "Code from the future");
+                    let mut entry = prev.clone().expect("Unknown first code");
+                    let first = entry[0];
+                    entry.push(first);
+                    entry
+                }
+            };
+            if let Some(mut learned) = prev {
+                if table.len() < 1 << GIF_MAX_CODE_SIZE {
+                    learned.push(entry[0]);
+                    table.push(learned);
+                }
+            }
+            if table.len() == 1 << size && size < GIF_MAX_CODE_SIZE {
+                size += 1;
+            }
+            out.extend_from_slice(&entry);
+            prev = Some(entry);
+        }
+    }
+
+    /// Returns the RGB pixels of each frame of a GIF written by `Animation`
+    fn decode_gif(data: &[u8]) -> Vec<Vec<[u8; 3]>> {
+        assert_eq!(&data[..6], b"GIF89a");
+        // Header, logical screen descriptor and the looping extension
+        let mut pos = 13 + 19;
+        let mut frames = Vec::new();
+        while data[pos] != 0x3b {
+            // Graphic control extension and image descriptor
+            assert_eq!(&data[pos..pos + 3], &[0x21, 0xf9, 4]);
+            pos += 8;
+            assert_eq!(data[pos], 0x2c);
+            let bits = (data[pos + 9] & 7) + 1;
+            pos += 10;
+            let palette: Vec<[u8; 3]> = data[pos..pos + 3 * (1 << bits)]
+                .chunks(3)
+                .map(|c| [c[0], c[1], c[2]])
+                .collect();
+            pos += palette.len() * 3;
+            let min_code_size = data[pos];
+            pos += 1;
+            let mut lzw_data = Vec::new();
+            while data[pos] != 0 {
+                let len = data[pos] as usize;
+                lzw_data.extend_from_slice(&data[pos + 1..pos + 1 + len]);
+                pos += 1 + len;
+            }
+            pos += 1;
+            frames.push(unlzw(&lzw_data, min_code_size)
+                .iter()
+                .map(|&i| palette[i as usize])
+                .collect());
+        }
+        assert_eq!(pos + 1, data.len());
+        frames
+    }
+
+    fn rgb(img: &Image) -> Vec<[u8; 3]> {
+        img.pixels.chunks(4).map(|p| [p[0], p[1], p[2]]).collect()
+    }
+
+    #[test]
+    fn gif_round_trip() {
+        // Noise in 200 colours needs several times 4096 codes, so the table
+        // is cleared repeatedly
+        let mut seed = 7u32;
+        let noisy: Vec<Image> = (0..2)
+            .map(|_| {
+                let mut img = Image::new(128, 96);
+                for x in 0..128 {
+                    for y in 0..96 {
+                        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                        let c = ((seed >> 16) % 200) as f32 / 200.0;
+                        img.blend(x, y, [c, 1.0 - c, 0.5, 1.0]);
+                    }
+                }
+                img
+            })
+            .collect();
+        let sequence = [&noisy[0], &noisy[1], &noisy[0]];
+        let mut anim = Animation::new(AnimationFormat::Gif, 128, 96);
+        for img in &sequence {
+            anim.push(img, 0.05);
+        }
+        let decoded = decode_gif(&anim.finish());
+        assert_eq!(decoded.len(), sequence.len());
+        for (img, pixels) in sequence.iter().zip(&decoded) {
+            assert!(rgb(img) == *pixels);
+        }
+    }
+
+    #[test]
+    fn apng_chunks() {
+        let mut anim = Animation::new(AnimationFormat::Apng, 64, 48);
+        for img in &frames() {
+            anim.push(img, 0.05);
+        }
+        let data = anim.finish();
+        let mut pos = 8;
+        let mut kinds = Vec::new();
+        let mut sequence = Vec::new();
+        let mut num_frames = None;
+        while pos < data.len() {
+            let len = ((data[pos] as usize) << 24) | ((data[pos + 1] as usize) << 16) |
+                      ((data[pos + 2] as usize) << 8) |
+                      data[pos + 3] as usize;
+            let kind = &data[pos + 4..pos + 8];
+            let body = &data[pos + 8..pos + 8 + len];
+            let be32 = |b: &[u8]| {
+                ((b[0] as u32) << 24) | ((b[1] as u32) << 16) | ((b[2] as u32) << 8) | b[3] as u32
+            };
+            assert_eq!(be32(&data[pos + 8 + len..]), crc32(&data[pos + 4..pos + 8 + len]));
+            match kind {
+                b"acTL" => num_frames = Some(be32(body)),
+                b"fcTL" | b"fdAT" => sequence.push(be32(body)),
+                _ => {}
+            }
+            kinds.push(String::from_utf8(kind.to_vec()).unwrap());
+            pos += 12 + len;
+        }
+        assert_eq!(num_frames, Some(3));
+        assert_eq!(sequence, (0..5).collect::<Vec<_>>());
+        assert_eq!(kinds,
+                   ["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]);
+    }
+}
@@ -0,0 +1,178 @@
+use catchit::{Event, State, Scalar};
+
+/// The `score_coeff` at which the music reaches its maximum intensity
+const MAX_INTENSITY_SCORE_COEFF: Scalar = 3.0;
+
+/// All sound effects the game knows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sound {
+    PreyCaught,
+    SwitchHit,
+    ForceEnabled,
+    GameOver,
+}
+
+/// Something able to make noise, like a sound card
+pub trait AudioSink {
+    /// Play the given sound once, at a volume from 0 to 1
+    fn play(&mut self, sound: Sound, volume: f32);
+
+    /// Adjust the background music. `intensity` ranges from 0 (calm) to 1
+    /// (frantic), and a `volume` of 0 mutes the music.
+    fn set_music(&mut self, intensity: f32, volume: f32);
+}
+
+impl<S: AudioSink + ?Sized> AudioSink for Box<S> {
+    fn play(&mut self, sound: Sound, volume: f32) {
+        (**self).play(sound, volume)
+    }
+
+    fn set_music(&mut self, intensity: f32, volume: f32) {
+        (**self).set_music(intensity, volume)
+    }
+}
+
+/// A sink which stays silent
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn play(&mut self, _sound: Sound, _volume: f32) {}
+    fn set_music(&mut self, _intensity: f32, _volume: f32) {}
+}
+
+/// Volume levels, each ranging from 0 to 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Volume {
+    pub master: f32,
+    pub effects: f32,
+    pub music: f32,
+}
+
+impl Default for Volume {
+    fn default() -> Volume {
+        Volume {
+            master: 0.8,
+            effects: 1.0,
+            music: 0.5,
+        }
+    }
+}
+
+/// Decides which sounds to play in response to what happens in the game
+pub struct Audio<S: AudioSink> {
+    sink: S,
+    pub volume: Volume,
+}
+
+impl<S: AudioSink> Audio<S> {
+    pub fn new(sink: S, volume: Volume) -> Audio<S> {
+        Audio {
+            sink: sink,
+            volume: volume,
+        }
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    fn play(&mut self, sound: Sound) {
+        let volume = self.volume.master * self.volume.effects;
+        if volume > 0.0 {
+            self.sink.play(sound, volume);
+        }
+    }
+
+    /// Play the sound matching the given engine event, if there is one
+    pub fn on_event(&mut self, event: &Event) {
+        let sound = match *event {
+            Event::PreyCaught { .. } |
+            Event::WaveCleared { .. } |
+            Event::CampaignComplete => Sound::PreyCaught,
+            Event::SwitchHit { .. } => Sound::SwitchHit,
+            Event::GameOver { .. } | Event::TimeUp => Sound::GameOver,
+            Event::ObstacleDespawned { .. } => return,
+        };
+        self.play(sound)
+    }
+
+    /// To be called whenever the hunter's force is switched
+    pub fn on_force(&mut self, enabled: bool) {
+        if enabled {
+            self.play(Sound::ForceEnabled);
+        }
+    }
+
+    /// Let the music follow the score multiplier of the given game, or calm it
+    /// down if there is no game.
+    pub fn update_music(&mut self, state: Option<&State>) {
+        let intensity = state.map(|s| {
+                ((s.score_coeff - 1.0) / (MAX_INTENSITY_SCORE_COEFF - 1.0)).max(0.0).min(1.0)
+            })
+            .unwrap_or(0.0);
+        let volume = self.volume.master * self.volume.music;
+        self.sink.set_music(intensity as f32, volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catchit::{Event, Engine};
+
+    /// A sink which remembers what it was asked to do, without making a sound
+    #[derive(Debug, Clone, PartialEq, Default)]
+    struct RecordingSink {
+        /// All sounds played so far, along with their volume
+        sounds: Vec<(Sound, f32)>,
+        /// The last music settings, as `(intensity, volume)`
+        music: Option<(f32, f32)>,
+    }
+
+    impl AudioSink for RecordingSink {
+        fn play(&mut self, sound: Sound, volume: f32) {
+            self.sounds.push((sound, volume));
+        }
+
+        fn set_music(&mut self, intensity: f32, volume: f32) {
+            self.music = Some((intensity, volume));
+        }
+    }
+
+    #[test]
+    fn events_and_volume() {
+        let mut audio = Audio::new(RecordingSink::default(),
+                                   Volume {
+                                       master: 0.5,
+                                       effects: 0.5,
+                                       music: 1.0,
+                                   });
+        audio.on_event(&Event::PreyCaught {
+            pos: [0.0, 0.0],
+            score: 10,
+        });
+        audio.on_force(false);
+        audio.on_force(true);
+        audio.on_event(&Event::GameOver { pos: [0.0, 0.0] });
+        assert_eq!(audio.sink().sounds,
+                   vec![(Sound::PreyCaught, 0.25),
+                        (Sound::ForceEnabled, 0.25),
+                        (Sound::GameOver, 0.25)]);
+
+        audio.volume.effects = 0.0;
+        audio.on_force(true);
+        assert_eq!(audio.sink().sounds.len(), 3);
+
+        audio.update_music(None);
+        assert_eq!(audio.sink().music, Some((0.0, 0.5)));
+
+        let mut state = Engine::from_field([400.0, 400.0]).state().clone().unwrap();
+        state.score_coeff = 2.0;
+        audio.update_music(Some(&state));
+        assert_eq!(audio.sink().music, Some((0.5, 0.5)));
+        state.score_coeff = 10.0;
+        audio.update_music(Some(&state));
+        assert_eq!(audio.sink().music, Some((1.0, 0.5)));
+    }
+}
@@ -0,0 +1,27 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+const CONFIG_DIR_NAME: &'static str = ".catchit";
+
+/// The directory holding all of our persistent files, like key bindings.
+///
+/// Falls back to the current working directory if there is no home directory.
+pub fn config_dir() -> PathBuf {
+    env::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(CONFIG_DIR_NAME)
+}
+
+pub fn read_file(path: &Path) -> io::Result<String> {
+    let mut content = String::new();
+    try!(try!(File::open(path)).read_to_string(&mut content));
+    Ok(content)
+}
+
+/// Writes the given content, creating all parent directories as needed
+pub fn write_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        try!(fs::create_dir_all(dir));
+    }
+    try!(File::create(path)).write_all(content)
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A day of the gregorian calendar, which identifies a daily challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+fn is_leap_year(year: u32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// The amount of days in the given month, counted from 1
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    /// The current day in UTC, which is the same everywhere at any moment
+    pub fn today() -> Date {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Date::from_days(secs / SECONDS_PER_DAY)
+    }
+
+    /// The date the given number of days after 1970-01-01
+    pub fn from_days(days: u64) -> Date {
+        // Counts in eras of 400 years, starting at 0000-03-01 to have the leap day last
+        let days = days + 719468;
+        let era = days / 146097;
+        let day_of_era = days % 146097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 -
+                           day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+        let month = if month_from_march < 10 {
+            month_from_march + 3
+        } else {
+            month_from_march - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date {
+            year: year as u32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Parses dates like `2016-10-18`
+    pub fn parse(s: &str) -> Option<Date> {
+        let parts = match s.split('-').map(|p| p.parse().ok()).collect::<Option<Vec<u32>>>() {
+            Some(parts) => parts,
+            None => return None,
+        };
+        if parts.len() != 3 || parts[1] == 0 || parts[1] > 12 || parts[2] == 0 ||
+           parts[2] > days_in_month(parts[0], parts[1]) {
+            return None;
+        }
+        Some(Date {
+            year: parts[0],
+            month: parts[1],
+            day: parts[2],
+        })
+    }
+
+    /// The seed of this day's challenge, which is the same on every machine
+    pub fn seed(&self) -> u64 {
+        // 64 bit FNV-1a, as the hashers of the standard library may change
+        let mut hash = 0xcbf29ce484222325u64;
+        for b in format!("catchit-daily-{}", self).bytes() {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_and_seeds() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(17092).to_string(), "2016-10-18");
+
+        let date = Date::parse("2016-10-18").unwrap();
+        assert_eq!(date, Date::from_days(17092));
+        assert_eq!(date.seed(), Date::from_days(17092).seed());
+        assert!(date.seed() != Date::from_days(17093).seed());
+        assert!(Date::parse("2016-02-29").is_some() && Date::parse("2000-02-29").is_some());
+        for invalid in &["2016-13-01", "2016-10", "2016-10-18-1", "today", "2016-10-x",
+                         "2016-02-30", "2015-02-29", "1900-02-29", "2016-04-31", "2016-10-32"] {
+            assert_eq!(Date::parse(invalid), None);
+        }
+    }
+}
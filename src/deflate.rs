@@ -0,0 +1,299 @@
+//! A small zlib compressor, using LZ77 with fixed Huffman codes.
+//!
+//! It compresses the large single-coloured areas of our images well, which is
+//! all we need, and its output only depends on its input.
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// How many earlier positions with the same hash are tried when looking for a match
+const MAX_CHAIN: usize = 32;
+const HASH_BITS: usize = 15;
+const END_OF_BLOCK: u16 = 256;
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35,
+                                43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4,
+                                4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+                                  257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+                                  8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8,
+                                  9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Writes bits starting with the least significant one, as deflate requires
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    num_bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, num_bits: u8) {
+        self.bits |= value << self.num_bits;
+        self.num_bits += num_bits;
+        while self.num_bits >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.num_bits -= 8;
+        }
+    }
+
+    /// Huffman codes are defined starting with their most significant bit
+    fn write_code(&mut self, code: u32, num_bits: u8) {
+        let mut reversed = 0;
+        for bit in 0..num_bits {
+            reversed |= ((code >> bit) & 1) << (num_bits - 1 - bit);
+        }
+        self.write(reversed, num_bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.num_bits > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+/// Writes a literal byte, or a length code, using the fixed Huffman code
+fn write_symbol(w: &mut BitWriter, symbol: u16) {
+    let s = symbol as u32;
+    if s < 144 {
+        w.write_code(0x30 + s, 8);
+    } else if s < 256 {
+        w.write_code(0x190 + s - 144, 9);
+    } else if s < 280 {
+        w.write_code(s - 256, 7);
+    } else {
+        w.write_code(0xc0 + s - 280, 8);
+    }
+}
+
+fn write_match(w: &mut BitWriter, length: usize, distance: usize) {
+    let lid = LENGTH_BASE.iter().rposition(|&b| b as usize <= length).unwrap();
+    write_symbol(w, 257 + lid as u16);
+    w.write((length - LENGTH_BASE[lid] as usize) as u32, LENGTH_EXTRA[lid]);
+
+    let did = DISTANCE_BASE.iter().rposition(|&b| b as usize <= distance).unwrap();
+    w.write_code(did as u32, 5);
+    w.write((distance - DISTANCE_BASE[did] as usize) as u32, DISTANCE_EXTRA[did]);
+}
+
+fn hash(data: &[u8], pos: usize) -> usize {
+    let v = (data[pos] as usize) << 16 | (data[pos + 1] as usize) << 8 | data[pos + 2] as usize;
+    (v.wrapping_mul(2654435761) >> 8) & ((1 << HASH_BITS) - 1)
+}
+
+/// Remember `pos` as the most recent position of its hash
+fn insert(data: &[u8], pos: usize, head: &mut [usize], prev: &mut [usize]) {
+    if pos + MIN_MATCH <= data.len() {
+        let h = hash(data, pos);
+        prev[pos % WINDOW_SIZE] = head[h];
+        head[h] = pos;
+    }
+}
+
+/// Compress `data` into a single deflate block
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter {
+        out: Vec::with_capacity(data.len() / 4),
+        bits: 0,
+        num_bits: 0,
+    };
+    // Final block, compressed with fixed Huffman codes
+    w.write(1, 1);
+    w.write(1, 2);
+
+    // Most recent position per hash, and the previous position with the same hash
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut best = (0, 0);
+        if pos + MIN_MATCH <= data.len() {
+            let max_len = (data.len() - pos).min(MAX_MATCH);
+            let mut candidate = head[hash(data, pos)];
+            let mut chain = 0;
+            while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE &&
+                  chain < MAX_CHAIN {
+                let len = data[candidate..]
+                    .iter()
+                    .zip(&data[pos..pos + max_len])
+                    .take_while(|&(a, b)| a == b)
+                    .count();
+                if len > best.0 {
+                    best = (len, pos - candidate);
+                    if len == max_len {
+                        break;
+                    }
+                }
+                let next = prev[candidate % WINDOW_SIZE];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        if best.0 >= MIN_MATCH {
+            write_match(&mut w, best.0, best.1);
+            for p in pos..pos + best.0 {
+                insert(data, p, &mut head, &mut prev);
+            }
+            pos += best.0;
+        } else {
+            write_symbol(&mut w, data[pos] as u16);
+            insert(data, pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+    write_symbol(&mut w, END_OF_BLOCK);
+    w.finish()
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // Small enough chunks to never overflow before taking the modulus
+    for chunk in data.chunks(1024) {
+        for &v in chunk {
+            a += v as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// Compress `data` into a zlib stream, as used by PNG
+pub fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    out.extend(deflate(data));
+    let checksum = adler32(data);
+    out.extend_from_slice(&[(checksum >> 24) as u8,
+                            (checksum >> 16) as u8,
+                            (checksum >> 8) as u8,
+                            checksum as u8]);
+    out
+}
+
+
+/// Reads bits starting with the least significant one
+#[cfg(test)]
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+#[cfg(test)]
+impl<'a> BitReader<'a> {
+    fn read(&mut self, num_bits: u8) -> u32 {
+        let mut value = 0;
+        for bit in 0..num_bits {
+            let byte = self.data[self.pos / 8];
+            value |= (((byte >> (self.pos % 8)) & 1) as u32) << bit;
+            self.pos += 1;
+        }
+        value
+    }
+
+    /// Huffman codes are defined starting with their most significant bit
+    fn read_code(&mut self, num_bits: u8) -> u32 {
+        (0..num_bits).fold(0, |code, _| (code << 1) | self.read(1))
+    }
+
+    /// Reads a literal byte or a length code of the fixed Huffman code
+    fn read_symbol(&mut self) -> u16 {
+        let code = self.read_code(7);
+        if code < 0x18 {
+            return 256 + code as u16;
+        }
+        let code = (code << 1) | self.read(1);
+        if code < 0xc0 {
+            return code as u16 - 0x30;
+        }
+        if code < 0xc8 {
+            return 280 + code as u16 - 0xc0;
+        }
+        (((code << 1) | self.read(1)) - 0x190 + 144) as u16
+    }
+}
+
+/// Decompresses stored blocks and blocks with fixed Huffman codes, which covers
+/// everything `deflate()` ever wrote. Panics on anything else.
+#[cfg(test)]
+pub fn inflate(data: &[u8]) -> Vec<u8> {
+    let mut r = BitReader {
+        data: data,
+        pos: 0,
+    };
+    let mut out: Vec<u8> = Vec::new();
+    loop {
+        let last = r.read(1) == 1;
+        match r.read(2) {
+            0 => {
+                let start = (r.pos + 7) / 8;
+                let len = data[start] as usize | (data[start + 1] as usize) << 8;
+                out.extend_from_slice(&data[start + 4..start + 4 + len]);
+                r.pos = (start + 4 + len) * 8;
+            }
+            1 => {
+                loop {
+                    let symbol = r.read_symbol();
+                    if symbol < END_OF_BLOCK {
+                        out.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == END_OF_BLOCK {
+                        break;
+                    }
+                    let lid = (symbol - 257) as usize;
+                    let length = LENGTH_BASE[lid] as usize + r.read(LENGTH_EXTRA[lid]) as usize;
+                    let did = r.read_code(5) as usize;
+                    let distance = DISTANCE_BASE[did] as usize +
+                                   r.read(DISTANCE_EXTRA[did]) as usize;
+                    let start = out.len() - distance;
+                    for i in 0..length {
+                        let byte = out[start + i];
+                        out.push(byte);
+                    }
+                }
+            }
+            kind => panic!("Unsupported block type {}", kind),
+        }
+        if last {
+            return out;
+        }
+    }
+}
+
+/// The inverse of `zlib()`, checking the checksum
+#[cfg(test)]
+pub fn unzlib(data: &[u8]) -> Vec<u8> {
+    let out = inflate(&data[2..data.len() - 4]);
+    let checksum = &data[data.len() - 4..];
+    let expected = adler32(&out);
+    assert_eq!(checksum,
+               &[(expected >> 24) as u8, (expected >> 16) as u8, (expected >> 8) as u8,
+                 expected as u8]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut data = vec![7; 1000];
+        data.extend((0..5000u32).map(|v| (v * v % 251) as u8));
+        data.extend(b"abcabcabcabcabd".iter().cloned());
+        for input in &[&data[..], &data[..1], &[][..]] {
+            assert_eq!(&unzlib(&zlib(input))[..], *input);
+        }
+        assert!(zlib(&data).len() < data.len() / 2);
+    }
+}
@@ -1,17 +1,27 @@
 use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::mem;
 
-use rand::{self, Rng};
+use rand::{self, Rng, SeedableRng};
 use vecmath::{vec2_len, vec2_sub, vec2_scale, vec2_add, vec2_normalized};
 
-use types::{Object, State, Extent, Scalar, Pt, Position, Velocity, Hunter, Obstacle};
+use types::{Object, State, Extent, Scalar, Position, Velocity, Hunter, Obstacle, Event,
+            GameMode, ObstacleKind, WaveStatus, ObstacleLimits, RecycleStrategy, Physics,
+            Movement, Spring, ScoreSource, ScoreEntry, COMBO_WINDOW};
 use types::ObstacleKind::*;
 use types::CollisionShape::*;
 use transition::Transition;
+use replay::{Replay, Input, Outcome};
+use daily::Date;
+use level::{Level, SpawnTrigger};
+use spawn::{SpawnPolicy, RandomPolicy, clamp_to_field, random_position};
 use transition::TransitionState::*;
 use transition::TransitionDirection::*;
 
 
 const MIN_FIELD_MARGIN: Scalar = 30.0;
+/// The least width and height of the playing field
+pub const MIN_FIELD_SIZE: Scalar = 320.0;
 const FIELD_VELOCITY_COEFF: Scalar = 0.4;
 const OBSTACLE_SIZE_COEFF: Scalar = 0.3;
 const MIN_OBSTACLE_TO_HUNTER_COEFF: Scalar = 0.1;
@@ -23,68 +33,129 @@ const ATTRACTIVE_FORCE_COEFF: Scalar = 1.25;
 const SPECIAL_OBSTACLE_PROBABILITY: f32 = 0.1;
 const HUNTER_FORCE: Scalar = 1.0 * 0.1;
 const HUNTER_FORCE_SIZE_COEFF: Scalar = 1.5;
+/// Turns the strength of the hunter's force into an acceleration. The force used to
+/// be applied once per frame, and this keeps its effect the same at 60 frames per second.
+const HUNTER_FORCE_RATE: Scalar = 60.0;
+/// The mass of switches, relative to deadly obstacles which are half their size
+const SWITCH_MASS: Scalar = 2.0;
+/// The longest step in which the hunter's spring is integrated, in seconds
+const MAX_SPRING_STEP: f64 = 1.0 / 120.0;
 const SCORE_PER_PREY: Scalar = 10.0;
+/// The combo stops multiplying the score of prey beyond this many catches in a row
+const MAX_COMBO_MULTIPLIER: u32 = 5;
 const SCORE_COEFF_INCREMENT_MULTIPLIER: Scalar = 0.1;
 const SPECIAL_OBSTACLE_STATE_SCORE_MULTIPLIER: Scalar = 2.0;
+/// In `GameMode::Survival`, points for every second alive
+const SURVIVAL_SCORE_PER_SECOND: u32 = 5;
+/// In `GameMode::Survival`, seconds between new obstacles
+const SURVIVAL_OBSTACLE_INTERVAL: f64 = 4.0;
+/// The most obstacles a spawn of a wave adds in one update, so that a long update
+/// can't flood the field
+const MAX_SPAWNS_PER_UPDATE: u32 = 8;
+/// Seconds a single step advances the game in step mode
+const STEP_TIME: f64 = 1.0 / 60.0;
+
+/// Adds an obstacle of the given kind and lifetime to the state
+type SpawnObstacle<'a> = FnMut(&mut State, Option<ObstacleKind>, Option<f64>) + 'a;
 
 /// The engine implements the game logic
 ///
-/// It relies on user input given as 2d coordinates
+/// It relies on user input given as 2d coordinates. All input of the current game
+/// is recorded, and can be played back using its `replay()`.
 pub struct Engine {
     state: Option<State>,
     min_distance: Scalar,
     rng: RefCell<rand::XorShiftRng>,
+    events: Vec<Event>,
+    replay: Replay,
+    mode: GameMode,
+    movement: Movement,
+    campaign: Option<Campaign>,
+    spawn_policy: Box<SpawnPolicy>,
+    limits: ObstacleLimits,
+    physics: Physics,
+    /// Multiplies the time passed in `update()`
+    time_scale: f64,
+    step_mode: bool,
+    /// Set if the next update in step mode should advance the game
+    step_requested: bool,
+    /// Game time passed during the last call to `update()`, in seconds
+    advanced: f64,
+    /// Seconds of play kept in `history`
+    rewind_duration: f64,
+    /// The states after the latest updates, oldest first
+    history: VecDeque<Snapshot>,
+    /// Set once the current game was rewound
+    rewound: bool,
+    /// Set once time of the current game passed scaled or in steps
+    time_altered: bool,
 }
 
-impl Engine {
-    fn rnd_obj_pos_in_field(field: &Extent,
-                            half_size: Pt,
-                            rng: &mut rand::XorShiftRng)
-                            -> Position {
-        Self::clamp_to_field(field,
-                             half_size,
-                             [rng.gen_range(0.0, field[0]), rng.gen_range(0.0, field[1])])
-    }
+/// Everything needed to continue a game from an earlier point
+#[derive(Clone)]
+struct Snapshot {
+    /// The state without its ledger, which only ever grows and isn't copied
+    state: State,
+    /// The amount of entries the ledger had at this point
+    ledger_len: usize,
+    rng: rand::XorShiftRng,
+    /// What the campaign spawned in its current wave, if there is one
+    spawned: Option<Vec<u32>>,
+    /// The amount of inputs recorded up to this point
+    inputs: usize,
+}
 
-    fn clamp_to_field(field: &Extent, half_size: Pt, mut pos: Position) -> Position {
-        if pos[0] - half_size < 0.0 {
-            pos[0] = half_size;
-        }
-        if pos[0] + half_size > field[0] {
-            pos[0] = field[0] - half_size;
-        }
-        if pos[1] - half_size < 0.0 {
-            pos[1] = half_size;
-        }
-        if pos[1] + half_size > field[1] {
-            pos[1] = field[1] - half_size;
-        }
-        pos
-    }
+/// Levels played in sequence, which decide when obstacles appear
+struct Campaign {
+    levels: Vec<Level>,
+    /// How many obstacles each spawn of the current wave created so far
+    spawned: Vec<u32>,
+}
+
+fn rng_from_seed(seed: u64) -> rand::XorShiftRng {
+    let (lo, hi) = (seed as u32, (seed >> 32) as u32);
+    // The generator must not be seeded with zeroes only
+    rand::XorShiftRng::from_seed([lo, hi, lo ^ 0x9e3779b9, hi ^ 0x7f4a7c15])
+}
+
+/// How often a multiple of `interval` was passed when going from `before` to `after`
+fn intervals_passed(before: f64, after: f64, interval: f64) -> u32 {
+    ((after / interval).floor() - (before / interval).floor()) as u32
+}
 
+/// How many spawns happen between `before` and `after` seconds, for spawns every
+/// `every` seconds with the first one at `start`
+fn spawns_due(start: f64, every: f64, before: f64, after: f64) -> u32 {
+    let spawned_until = |t: f64| if t < start { 0 } else { ((t - start) / every) as u32 + 1 };
+    spawned_until(after) - spawned_until(before)
+}
+
+impl Engine {
     fn hunter_half_size(field: &Extent) -> Scalar {
         let margin = (field[0].min(field[1]) * 0.05).max(MIN_FIELD_MARGIN);
         (margin - (MIN_FIELD_MARGIN / 6.0)) / 2.0
     }
 
-    fn state_from_field(field: Extent) -> State {
-        assert!(field[0].min(field[1]) >= 320.0,
+    fn state_from_field(field: Extent, mode: GameMode, rng: &mut rand::XorShiftRng) -> State {
+        assert!(field[0].min(field[1]) >= MIN_FIELD_SIZE,
                 "Playing field is too small");
         let half_size = Self::hunter_half_size(&field);
 
-        let mut rng = rand::weak_rng();
-        let prey_pos = Self::rnd_obj_pos_in_field(&field, half_size, &mut rng);
+        let prey_pos = random_position(&field, half_size, rng);
+        let hunter_pos = [-half_size * 2.0, -half_size * 2.0];
 
         State {
+            mode: mode,
             field: field,
             hunter: Hunter {
                 object: Object {
-                    pos: [-half_size * 2.0, -half_size * 2.0],
+                    pos: hunter_pos,
                     half_size: half_size,
                     shape: Circle,
                 },
                 force: 0.0,
                 velocity: [0.0, 0.0],
+                target: hunter_pos,
             },
             prey: Object {
                 pos: prey_pos,
@@ -99,6 +170,11 @@ impl Engine {
             score: 0,
             score_coeff: 1.0,
             last_dt: 1.0,
+            time: 0.0,
+            wave: None,
+            combo: 0,
+            combo_time_left: 0.0,
+            ledger: Vec::new(),
         }
     }
 
@@ -106,16 +182,15 @@ impl Engine {
         self.min_distance = (state.field[0].powi(2) + state.field[1].powi(2)).sqrt() *
                             MIN_OBSTACLE_TO_HUNTER_COEFF;
         self.state = Some(state);
+        self.history.clear();
+        self.rewound = false;
+        self.time_altered = false;
     }
 
 
-    fn new_obstacle(rng: &mut rand::XorShiftRng, s: &mut State, min_distance: Scalar) {
-        s.prey.pos = Self::rnd_obj_pos_in_field(&s.field, s.prey.half_size, rng);
-
-        let mut half_size = s.hunter.object.half_size * OBSTACLE_SIZE_COEFF;
-        let kind = match rng.gen_range(0.0f32, 1.0) {
+    fn random_obstacle_kind(rng: &mut rand::XorShiftRng) -> ObstacleKind {
+        match rng.gen_range(0.0f32, 1.0) {
             p if p < SPECIAL_OBSTACLE_PROBABILITY => {
-                half_size *= 2.0;
                 if rng.gen_range(0.0f32, 1.0) > 0.5 {
                     InvisibiltySwitch
                 } else {
@@ -123,41 +198,275 @@ impl Engine {
                 }
             }
             _ => Deadly,
-        };
+        }
+    }
+
+    fn obstacle_half_size(s: &State, kind: &ObstacleKind) -> Scalar {
+        let half_size = s.hunter.object.half_size * OBSTACLE_SIZE_COEFF;
+        match *kind {
+            Deadly => half_size,
+            _ => half_size * 2.0,
+        }
+    }
+
+    fn obstacle_mass(kind: &ObstacleKind) -> Scalar {
+        match *kind {
+            Deadly => 1.0,
+            _ => SWITCH_MASS,
+        }
+    }
+
+    /// Adds an obstacle of the given kind, or a random one, where the policy
+    /// places it
+    fn new_obstacle(policy: &SpawnPolicy,
+                    rng: &mut rand::XorShiftRng,
+                    s: &mut State,
+                    min_distance: Scalar,
+                    kind: Option<ObstacleKind>,
+                    lifetime: Option<f64>) {
+        let kind = kind.unwrap_or_else(|| Self::random_obstacle_kind(rng));
+        if kind == Deadly && s.mode == GameMode::Zen {
+            return;
+        }
+        let half_size = Self::obstacle_half_size(s, &kind);
         let vel: Velocity = [rng.gen_range(-s.field[0] * FIELD_VELOCITY_COEFF,
                                            s.field[0] * FIELD_VELOCITY_COEFF),
                              rng.gen_range(-s.field[1] * FIELD_VELOCITY_COEFF,
                                            s.field[1] * FIELD_VELOCITY_COEFF)];
-
-        let mut pos = s.hunter.object.pos;
-        while vec2_len(vec2_sub(pos, s.hunter.object.pos)) < min_distance {
-            pos = Self::rnd_obj_pos_in_field(&s.field, s.prey.half_size, rng)
-        }
+        let (pos, vel) = policy.spawn(rng, s, half_size, min_distance, vel);
         s.obstacles.push(Obstacle {
+            mass: Self::obstacle_mass(&kind),
             kind: kind,
             object: Object {
-                pos: Self::clamp_to_field(&s.field, half_size, pos),
+                pos: clamp_to_field(&s.field, half_size, pos),
                 half_size: half_size,
                 shape: Circle,
             },
             velocity: vel,
+            age: 0.0,
+            lifetime: lifetime,
+            base_speed: vec2_len(vel),
+            departing: false,
         });
     }
 
-    fn advect_obstacles(s: &mut State, dt: f64) {
-        // Move and collide the obstacles.
-        for mut obstacle in &mut s.obstacles {
+    /// Moves `pos` straight away from the hunter until it is at least `min_distance`
+    /// from it, or to the opposite side if the field is in the way there
+    fn clear_of_hunter(s: &State,
+                       half_size: Scalar,
+                       pos: Position,
+                       min_distance: Scalar)
+                       -> Position {
+        let hunter = s.hunter.object.pos;
+        let distance = |p: Position| vec2_len(vec2_sub(p, hunter));
+        let pos = clamp_to_field(&s.field, half_size, pos);
+        if distance(pos) >= min_distance {
+            return pos;
+        }
+        let offset = vec2_sub(pos, hunter);
+        let direction = if vec2_len(offset) > 0.0 {
+            vec2_normalized(offset)
+        } else {
+            [1.0, 0.0]
+        };
+        let away = clamp_to_field(&s.field,
+                                  half_size,
+                                  vec2_add(hunter, vec2_scale(direction, min_distance)));
+        let opposite = clamp_to_field(&s.field,
+                                      half_size,
+                                      vec2_sub(hunter, vec2_scale(direction, min_distance)));
+        if distance(away) >= min_distance || distance(away) >= distance(opposite) {
+            away
+        } else {
+            opposite
+        }
+    }
+
+    /// Replace all obstacles with the initial ones of the given wave, keeping them at
+    /// least `min_distance` from the hunter
+    fn start_wave(campaign: &mut Campaign,
+                  s: &mut State,
+                  min_distance: Scalar,
+                  level: usize,
+                  wave: usize) {
+        let w = &campaign.levels[level].waves[wave];
+        s.obstacles.clear();
+        for spec in &w.obstacles {
+            if spec.kind == Deadly && s.mode == GameMode::Zen {
+                continue;
+            }
+            let half_size = Self::obstacle_half_size(s, &spec.kind);
+            let pos = [spec.pos[0] * s.field[0], spec.pos[1] * s.field[1]];
+            let pos = Self::clear_of_hunter(s, half_size, pos, min_distance);
+            let velocity = [spec.velocity[0] * s.field[0], spec.velocity[1] * s.field[1]];
+            s.obstacles.push(Obstacle {
+                kind: spec.kind.clone(),
+                object: Object {
+                    pos: pos,
+                    half_size: half_size,
+                    shape: Circle,
+                },
+                velocity: velocity,
+                mass: Self::obstacle_mass(&spec.kind),
+                base_speed: vec2_len(velocity),
+                age: 0.0,
+                lifetime: None,
+                departing: false,
+            });
+        }
+        campaign.spawned = vec![0; w.spawns.len()];
+        s.wave = Some(WaveStatus {
+            level: level,
+            wave: wave,
+            win: w.win.clone(),
+            caught: 0,
+            time: 0.0,
+        });
+    }
+
+    /// Spawn the obstacles of the current wave which are due, after `prey_caught` prey
+    /// were caught and the wave ran from `before` to `after` seconds. `spawn` adds an
+    /// obstacle of the given kind and lifetime.
+    fn spawn_wave_obstacles(campaign: &mut Campaign,
+                            s: &mut State,
+                            prey_caught: bool,
+                            before: f64,
+                            after: f64,
+                            spawn_obstacle: &mut SpawnObstacle) {
+        let (level, wave) = match s.wave {
+            Some(ref w) => (w.level, w.wave),
+            None => return,
+        };
+        for (spawn, spawned) in campaign.levels[level].waves[wave]
+            .spawns
+            .iter()
+            .zip(campaign.spawned.iter_mut()) {
+            let due = match spawn.trigger {
+                SpawnTrigger::PreyCaught => prey_caught as u32,
+                SpawnTrigger::Interval { start, every } => spawns_due(start, every, before, after),
+            };
+            let due = match spawn.count {
+                Some(max) => due.min(max - *spawned),
+                None => due,
+            };
+            let due = due.min(MAX_SPAWNS_PER_UPDATE);
+            for _ in 0..due {
+                spawn_obstacle(s, spawn.kind.clone(), spawn.lifetime);
+            }
+            *spawned += due;
+        }
+    }
+
+    /// Start the next wave if the current one is won. Returns true once the last
+    /// wave of the last level was won.
+    fn advance_wave(campaign: &mut Campaign,
+                    s: &mut State,
+                    min_distance: Scalar,
+                    events: &mut Vec<Event>)
+                    -> bool {
+        let (level, wave) = match s.wave {
+            Some(ref w) if w.is_won() => (w.level, w.wave),
+            _ => return false,
+        };
+        events.push(Event::WaveCleared {
+            level: level,
+            wave: wave,
+        });
+        if wave + 1 < campaign.levels[level].waves.len() {
+            Self::start_wave(campaign, s, min_distance, level, wave + 1);
+        } else if level + 1 < campaign.levels.len() {
+            Self::start_wave(campaign, s, min_distance, level + 1, 0);
+        } else {
+            events.push(Event::CampaignComplete);
+            return true;
+        }
+        false
+    }
+
+    /// Adds the points of `entry` to the score, and keeps it in the ledger.
+    /// Returns the points awarded.
+    fn award(s: &mut State, entry: ScoreEntry) -> u32 {
+        let points = entry.points;
+        s.score += points;
+        s.ledger.push(entry);
+        points
+    }
+
+    /// Pull the hunter towards its target, in steps short enough to keep the spring stable
+    fn follow_target(hunter: &mut Hunter, spring: &Spring, field: &Extent, dt: f64) {
+        let max_speed = spring.max_speed * field[0].min(field[1]);
+        let steps = (dt / MAX_SPRING_STEP).ceil().max(1.0);
+        let step = dt / steps;
+        for _ in 0..steps as usize {
+            let pull = vec2_scale(vec2_sub(hunter.target, hunter.object.pos), spring.stiffness);
+            let acceleration = vec2_sub(pull, vec2_scale(hunter.velocity, spring.damping));
+            let mut velocity = vec2_add(hunter.velocity, vec2_scale(acceleration, step));
+            let speed = vec2_len(velocity);
+            if speed > max_speed {
+                velocity = vec2_scale(velocity, max_speed / speed);
+            }
+            hunter.velocity = velocity;
+            hunter.object.pos = vec2_add(hunter.object.pos, vec2_scale(velocity, step));
+        }
+    }
+
+    /// Remove expired obstacles, and let the surplus ones fade out if there are too many
+    fn despawn_obstacles(s: &mut State, limits: &ObstacleLimits, events: &mut Vec<Event>) {
+        let (expired, obstacles) = s.obstacles.drain(..).partition(|o| o.is_expired());
+        s.obstacles = obstacles;
+        let expired: Vec<Obstacle> = expired;
+        for obstacle in expired {
+            events.push(Event::ObstacleDespawned {
+                kind: obstacle.kind,
+                pos: obstacle.object.pos,
+            });
+        }
+
+        let max_count = match limits.max_count {
+            Some(max_count) => max_count,
+            None => return,
+        };
+        let hunter_pos = s.hunter.object.pos;
+        let mut staying = s.obstacles.iter().filter(|o| !o.is_departing()).count();
+        while staying > max_count {
+            let recycled = s.obstacles
+                .iter_mut()
+                .filter(|o| !o.is_departing())
+                .fold(None, |best: Option<(Scalar, &mut Obstacle)>, o| {
+                    let rank = match limits.recycle {
+                        RecycleStrategy::Oldest => o.age,
+                        RecycleStrategy::Farthest => vec2_len(vec2_sub(o.object.pos, hunter_pos)),
+                    };
+                    match best {
+                        Some((best_rank, _)) if best_rank >= rank => best,
+                        _ => Some((rank, o)),
+                    }
+                });
+            if let Some((_, obstacle)) = recycled {
+                obstacle.leave();
+            }
+            staying -= 1;
+        }
+    }
+
+    /// Move and collide the obstacles, using semi-implicit Euler integration: the
+    /// hunter's force and drag change the velocity first, which then moves the obstacle.
+    fn advect_obstacles(s: &mut State, physics: &Physics, dt: f64) {
+        let max_speed = physics.max_speed * s.field[0].min(s.field[1]);
+        let drag_factor = (-physics.drag * dt).exp();
+        for obstacle in &mut s.obstacles {
+            obstacle.age += dt;
 
             let obj = &mut obstacle.object;
 
 
-            let repell_velocity = if s.hunter.force > 0.0 || s.attracting_force.current > 0.0 {
-                let vel = vec2_sub(obj.pos, s.hunter.object.pos);
-                let velocity_scale = vec2_len(vel) / (s.hunter.object.half_size * 2.0 * 4.0);
+            let force = if s.hunter.force > 0.0 || s.attracting_force.current > 0.0 {
+                let offset = vec2_sub(obj.pos, s.hunter.object.pos);
+                let distance_scale = vec2_len(offset) / s.hunter.force_radius();
 
-                if velocity_scale <= 1.0 {
-                    vec2_scale(vel,
-                               (1.0 - velocity_scale) *
+                if distance_scale <= 1.0 {
+                    vec2_scale(offset,
+                               (1.0 - distance_scale) * HUNTER_FORCE_RATE *
                                (s.hunter.force - s.attracting_force.current))
                 } else {
                     [0.0, 0.0]
@@ -165,7 +474,16 @@ impl Engine {
             } else {
                 [0.0, 0.0]
             };
-            obstacle.velocity = vec2_add(obstacle.velocity, repell_velocity);
+            let mut velocity = vec2_add(obstacle.velocity,
+                                        vec2_scale(force, dt / obstacle.mass));
+
+            // Drag only takes away the speed gained beyond the base speed
+            let speed = vec2_len(velocity);
+            if speed > obstacle.base_speed {
+                let slowed = obstacle.base_speed + (speed - obstacle.base_speed) * drag_factor;
+                velocity = vec2_scale(velocity, slowed.min(max_speed) / speed);
+            }
+            obstacle.velocity = velocity;
             obj.pos = vec2_add(obj.pos, vec2_scale(obstacle.velocity, dt));
 
             if obj.left() <= 0.0 || obj.right() >= s.field[0] {
@@ -176,7 +494,7 @@ impl Engine {
                 obstacle.velocity[1] = -obstacle.velocity[1];
             }
 
-            obj.pos = Self::clamp_to_field(&s.field, obj.half_size, obj.pos);
+            obj.pos = clamp_to_field(&s.field, obj.half_size, obj.pos);
         }
     }
 
@@ -186,49 +504,405 @@ impl Engine {
 }
 
 impl Engine {
+    /// A new game with a random seed
     pub fn from_field(field: Extent) -> Engine {
+        Self::from_seed(field, rand::random())
+    }
+
+    /// A new game whose random numbers are fully determined by `seed`
+    pub fn from_seed(field: Extent, seed: u64) -> Engine {
+        Self::from_replay(&Replay::new(seed, field))
+    }
+
+    /// A new game set up like the recorded one, which plays the same if given
+    /// the recorded input
+    pub fn from_replay(replay: &Replay) -> Engine {
         let mut e = Engine {
             state: None,
             min_distance: 0.0,
-            rng: RefCell::new(rand::weak_rng()),
+            rng: RefCell::new(rng_from_seed(replay.seed)),
+            events: Vec::new(),
+            replay: Replay::new(replay.seed, replay.field),
+            mode: replay.mode,
+            movement: replay.movement,
+            campaign: None,
+            spawn_policy: Box::new(RandomPolicy),
+            limits: ObstacleLimits::default(),
+            physics: Physics::default(),
+            time_scale: 1.0,
+            step_mode: false,
+            step_requested: false,
+            advanced: 0.0,
+            rewind_duration: 0.0,
+            history: VecDeque::new(),
+            rewound: false,
+            time_altered: false,
         };
-        e.set_state(Self::state_from_field(field));
+        e.reset_with_seed(replay.field, replay.seed);
+        e.replay.daily = replay.daily;
         e
     }
 
+    /// The rules of the current game
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Set the rules for all games started by the next reset
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+    }
+
+    /// How the hunter follows the player in the current game
+    pub fn movement(&self) -> Movement {
+        self.movement
+    }
+
+    /// Set how the hunter follows the player in all games started by the next reset
+    pub fn set_movement(&mut self, movement: Movement) {
+        self.movement = movement;
+    }
+
+    /// Decide where obstacles appear from now on, which is anywhere away from the
+    /// hunter by default.
+    ///
+    /// The policy is not part of the `replay()`, so games using a different one only
+    /// play back the same with that policy set.
+    pub fn set_spawn_policy(&mut self, policy: Box<SpawnPolicy>) {
+        self.spawn_policy = policy;
+    }
+
+    /// Restrict the lifetime and number of obstacles from now on, which are unlimited
+    /// by default.
+    ///
+    /// Like the spawn policy, the limits are not part of the `replay()`.
+    pub fn set_obstacle_limits(&mut self, limits: ObstacleLimits) {
+        self.limits = limits;
+    }
+
+    pub fn obstacle_limits(&self) -> &ObstacleLimits {
+        &self.limits
+    }
+
+    /// Change how obstacles react to the hunter's force from now on.
+    ///
+    /// Like the spawn policy, the physics are not part of the `replay()`.
+    pub fn set_physics(&mut self, physics: Physics) {
+        self.physics = physics;
+    }
+
+    pub fn physics(&self) -> &Physics {
+        &self.physics
+    }
+
+    /// Let the game run faster or slower than real time, like with a scale of 0.25
+    /// for slow motion. The replay records the scaled time, so it plays back at
+    /// normal speed.
+    pub fn set_time_scale(&mut self, scale: f64) {
+        self.time_scale = scale.max(0.0);
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// In step mode, `update()` ignores the time passed, and the game only advances
+    /// by a single tick of `STEP_TIME` seconds whenever a step was requested.
+    pub fn set_step_mode(&mut self, enabled: bool) {
+        self.step_mode = enabled;
+        self.step_requested = false;
+    }
+
+    pub fn step_mode(&self) -> bool {
+        self.step_mode
+    }
+
+    /// Make the next `update()` in step mode advance the game by one tick
+    pub fn request_step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// Game time passed during the last call to `update()`, in seconds, which
+    /// differs from real time if it is scaled or the engine is in step mode
+    pub fn advanced(&self) -> f64 {
+        self.advanced
+    }
+
+    /// Keep the last `seconds` of play, to be able to `rewind()` them. It is off
+    /// with zero seconds, which is the default.
+    pub fn set_rewind_duration(&mut self, seconds: f64) {
+        self.rewind_duration = seconds.max(0.0);
+        self.trim_history();
+    }
+
+    pub fn rewind_duration(&self) -> f64 {
+        self.rewind_duration
+    }
+
+    fn trim_history(&mut self) {
+        let newest = self.history.back().map_or(0.0, |s| s.state.time);
+        while self.history.front().map_or(false, |s| s.state.time < newest - self.rewind_duration) {
+            self.history.pop_front();
+        }
+    }
+
+    fn record_snapshot(&mut self) {
+        if self.rewind_duration <= 0.0 {
+            return;
+        }
+        if let Some(ref mut state) = self.state {
+            let ledger = mem::replace(&mut state.ledger, Vec::new());
+            self.history.push_back(Snapshot {
+                state: state.clone(),
+                ledger_len: ledger.len(),
+                rng: self.rng.borrow().clone(),
+                spawned: self.campaign.as_ref().map(|c| c.spawned.clone()),
+                inputs: self.replay.inputs.len(),
+            });
+            state.ledger = ledger;
+        }
+        self.trim_history();
+    }
+
+    /// Go back in time by up to `seconds`, to continue the game from there. The
+    /// inputs of the undone time are removed from the `replay()`.
+    ///
+    /// Returns the seconds actually rewound, which are zero once the game is over
+    /// or the oldest state is reached.
+    pub fn rewind(&mut self, seconds: f64) -> f64 {
+        let (now, mut ledger) = match self.state {
+            Some(ref mut s) => (s.time, mem::replace(&mut s.ledger, Vec::new())),
+            None => return 0.0,
+        };
+        // The oldest snapshot is kept, as there is nothing to go back to beyond it
+        while self.history.len() > 1 &&
+              self.history.back().map_or(false, |s| s.state.time > now - seconds) {
+            self.history.pop_back();
+        }
+        let snapshot = match self.history.back() {
+            Some(snapshot) if snapshot.state.time < now => snapshot.clone(),
+            _ => {
+                self.state.as_mut().unwrap().ledger = ledger;
+                return 0.0;
+            }
+        };
+
+        ledger.truncate(snapshot.ledger_len);
+        self.state = Some(State { ledger: ledger, ..snapshot.state });
+        *self.rng.borrow_mut() = snapshot.rng;
+        if let (Some(campaign), Some(spawned)) = (self.campaign.as_mut(), snapshot.spawned) {
+            campaign.spawned = spawned;
+        }
+        self.replay.inputs.truncate(snapshot.inputs);
+        self.events.clear();
+        self.advanced = 0.0;
+        self.rewound = true;
+        now - self.state.as_ref().unwrap().time
+    }
+
+    /// Continue the game which ended with `state`, as returned by `update()`, to
+    /// `rewind()` it to a point before it was over
+    pub fn resume(&mut self, state: State) {
+        self.state = Some(state);
+    }
+
+    /// Whether the current game was rewound at some point
+    pub fn is_rewound(&self) -> bool {
+        self.rewound
+    }
+
+    /// Whether the current game was rewound, or time passed at a different rate
+    /// than real time at some point, in slow motion or step by step. Such games are
+    /// practice.
+    pub fn is_practice(&self) -> bool {
+        self.rewound || self.time_altered
+    }
+
+    /// The states after each of the latest updates, oldest first, as far as they
+    /// are kept for rewinding. They remain available after the game is over, and
+    /// their ledgers are empty.
+    pub fn history(&self) -> Vec<&State> {
+        self.history.iter().map(|s| &s.state).collect()
+    }
+
     /// Reset the engine to use the given game-state.
     /// Can be used to setup a new game as well.
     pub fn reset(&mut self, field: Extent) {
-        self.set_state(Self::state_from_field(field));
+        self.reset_with_seed(field, rand::random());
+    }
+
+    /// Like `reset()`, but with the given seed for random numbers
+    pub fn reset_with_seed(&mut self, field: Extent, seed: u64) {
+        *self.rng.borrow_mut() = rng_from_seed(seed);
+        let state = Self::state_from_field(field, self.mode, &mut self.rng.borrow_mut());
+        self.set_state(state);
+        self.events.clear();
+        self.replay = Replay::new(seed, field);
+        self.replay.mode = self.mode;
+        self.replay.movement = self.movement;
+        self.campaign = None;
+    }
+
+    /// Like `reset()`, but playing the daily challenge of the given day
+    pub fn reset_daily(&mut self, field: Extent, date: Date) {
+        self.reset_with_seed(field, date.seed());
+        self.replay.daily = Some(date);
+    }
+
+    /// Start a new game which plays the waves of the given levels one after another.
+    /// Obstacles only appear as the waves define them, and the game ends once the
+    /// last wave was cleared.
+    ///
+    /// The levels are not part of the `replay()`, so such games can't be played back.
+    pub fn start_campaign(&mut self, field: Extent, levels: Vec<Level>) {
+        assert!(levels.iter().all(|l| !l.waves.is_empty()),
+                "Levels must have at least one wave");
+        self.reset(field);
+        if levels.is_empty() {
+            return;
+        }
+        let mut campaign = Campaign {
+            levels: levels,
+            spawned: Vec::new(),
+        };
+        if let Some(ref mut s) = self.state {
+            Self::start_wave(&mut campaign, s, self.min_distance, 0, 0);
+        }
+        self.campaign = Some(campaign);
     }
 
     /// Update the game state.
     ///
     /// If the returned value is the last game-state, it indicates that the player
     /// is game-over.
+    ///
+    /// All events which happened during the update are available through `events()`
+    /// afterwards.
+    ///
+    /// `dt` is multiplied by the `time_scale()`, or replaced by a step in step mode.
+    #[allow(clippy::result_large_err)]
     pub fn update(&mut self, dt: f64) -> Result<(), State> {
+        let dt = if !self.step_mode {
+            if self.time_scale != 1.0 {
+                self.time_altered = true;
+            }
+            dt * self.time_scale
+        } else if self.step_requested {
+            self.step_requested = false;
+            self.time_altered = true;
+            STEP_TIME
+        } else {
+            self.events.clear();
+            self.advanced = 0.0;
+            return Ok(());
+        };
+        self.advanced = dt;
+        let result = self.advance(dt);
+        if result.is_ok() {
+            self.record_snapshot();
+        }
+        result
+    }
+
+    /// Advance the game by exactly `dt` seconds
+    #[allow(clippy::result_large_err)]
+    fn advance(&mut self, dt: f64) -> Result<(), State> {
         let mut is_game_over = false;
+        self.events.clear();
 
         if let Some(ref mut s) = self.state {
+            self.replay.inputs.push(Input::Update(dt));
 
             s.last_dt = dt;
+            let time_before = s.time;
+            s.time += dt;
+            if let Movement::Inertial(ref spring) = self.movement {
+                Self::follow_target(&mut s.hunter, spring, &s.field, dt);
+                if Self::pos_out_of_field(&s.field, &s.hunter.object.pos) {
+                    s.score_coeff = 1.0;
+                }
+            }
+            let mut prey_caught = false;
+            let (policy, rng, min_distance) = (&*self.spawn_policy, &self.rng, self.min_distance);
+            let default_lifetime = self.limits.lifetime;
+            let mut spawn_obstacle = |s: &mut State, kind, lifetime: Option<f64>| {
+                Self::new_obstacle(policy,
+                                   &mut rng.borrow_mut(),
+                                   s,
+                                   min_distance,
+                                   kind,
+                                   lifetime.or(default_lifetime))
+            };
             if !Self::pos_out_of_field(&s.field, &s.hunter.object.pos) &&
                vec2_len(s.hunter.velocity) > 10.0 {
                 s.score_coeff += SCORE_COEFF_INCREMENT_MULTIPLIER * dt;
             }
 
+            if s.combo_time_left > 0.0 {
+                s.combo_time_left -= dt;
+                if s.combo_time_left <= 0.0 {
+                    s.combo = 0;
+                    s.combo_time_left = 0.0;
+                }
+            }
+
             if s.hunter.object.intersects(&s.prey) {
-                let mut multiplier = s.score_coeff;
+                s.combo += 1;
+                s.combo_time_left = COMBO_WINDOW;
+                let mut special = 1.0;
                 for transition in &[&s.obstacle_opacity, &s.attracting_force] {
                     if !transition.is_pristine() {
-                        multiplier *= SPECIAL_OBSTACLE_STATE_SCORE_MULTIPLIER;
+                        special *= SPECIAL_OBSTACLE_STATE_SCORE_MULTIPLIER;
                     }
                 }
-                s.score += (SCORE_PER_PREY * multiplier) as u32;
-                Self::new_obstacle(&mut self.rng.borrow_mut(), s, self.min_distance);
+                let combo = s.combo.min(MAX_COMBO_MULTIPLIER);
+                let entry = ScoreEntry::new(s.time,
+                                            ScoreSource::Prey,
+                                            SCORE_PER_PREY,
+                                            s.score_coeff,
+                                            combo,
+                                            special);
+                let score = Self::award(s, entry);
+                self.events.push(Event::PreyCaught {
+                    pos: s.prey.pos,
+                    score: score,
+                });
+                s.prey.pos = random_position(&s.field, s.prey.half_size, &mut rng.borrow_mut());
+                match s.wave {
+                    Some(ref mut w) => w.caught += 1,
+                    None => spawn_obstacle(s, None, None),
+                }
+                prey_caught = true;
             }// check hunter-prey intersection
 
-            Self::advect_obstacles(s, dt);
+            if let Some(ref mut campaign) = self.campaign {
+                let wave_time = s.wave.as_ref().map(|w| w.time).unwrap_or(0.0);
+                if let Some(ref mut w) = s.wave {
+                    w.time += dt;
+                }
+                Self::spawn_wave_obstacles(campaign,
+                                           s,
+                                           prey_caught,
+                                           wave_time,
+                                           wave_time + dt,
+                                           &mut spawn_obstacle);
+            }
+
+            if s.mode == GameMode::Survival {
+                let seconds = intervals_passed(time_before, s.time, 1.0);
+                if seconds > 0 {
+                    // Time alive isn't subject to any multiplier
+                    let base = (seconds * SURVIVAL_SCORE_PER_SECOND) as Scalar;
+                    let entry = ScoreEntry::new(s.time, ScoreSource::Survival, base, 1.0, 1, 1.0);
+                    Self::award(s, entry);
+                }
+                for _ in 0..intervals_passed(time_before, s.time, SURVIVAL_OBSTACLE_INTERVAL) {
+                    spawn_obstacle(s, None, None);
+                }
+            }
+
+            Self::advect_obstacles(s, &self.physics, dt);
+            Self::despawn_obstacles(s, &self.limits, &mut self.events);
 
             // advance transitions
             for &mut (ref mut t, duration) in &mut [(&mut s.obstacle_opacity,
@@ -262,10 +936,11 @@ impl Engine {
 
             // Handle obstacle hits
             for obstacle in &mut s.obstacles {
-                if obstacle.object.intersects(&s.hunter.object) {
+                if !obstacle.is_leaving() && obstacle.object.intersects(&s.hunter.object) {
                     match obstacle.kind {
                         Deadly => {
                             is_game_over = true;
+                            self.events.push(Event::GameOver { pos: obstacle.object.pos });
                             break;
                         }
                         InvisibiltySwitch | AttractiveForceSwitch => {
@@ -282,18 +957,43 @@ impl Engine {
 
                             if transition.state() == Start && transition.direction == FromTo {
                                 transition.advance(dt);
+                                self.events.push(Event::SwitchHit {
+                                    kind: obstacle.kind.clone(),
+                                    pos: obstacle.object.pos,
+                                });
                             }
                         }
                     }
                 }
             }
 
-            // hunter velocity only remains once we get a move input
-            s.hunter.velocity = [0.0, 0.0];
+            if let Some(ref mut campaign) = self.campaign {
+                if !is_game_over &&
+                   Self::advance_wave(campaign, s, self.min_distance, &mut self.events) {
+                    is_game_over = true;
+                }
+            }
+
+            if let Some(limit) = s.mode.time_limit() {
+                if !is_game_over && s.time >= limit {
+                    is_game_over = true;
+                    self.events.push(Event::TimeUp);
+                }
+            }
+
+            // hunter velocity only remains once we get a move input, unless it has momentum
+            if self.movement == Movement::Direct {
+                s.hunter.velocity = [0.0, 0.0];
+            }
         } // end have game state
 
         if is_game_over {
-            Err(self.state.take().unwrap())
+            let state = self.state.take().unwrap();
+            self.replay.outcome = Some(Outcome {
+                score: state.score,
+                ticks: self.replay.ticks(),
+            });
+            Err(state)
         } else {
             Ok(())
         }
@@ -303,9 +1003,43 @@ impl Engine {
         &self.state
     }
 
-    /// Position will be clamped into the playing field
+    /// Events which happened during the last call to `update()`
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// All input of the current game so far, or of the last one if it is over
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Pass recorded input on to the respective method
+    #[allow(clippy::result_large_err)]
+    pub fn apply(&mut self, input: &Input) -> Result<(), State> {
+        match *input {
+            Input::HunterPos(pos) => self.set_hunter_pos(pos),
+            Input::HunterForce(enabled) => self.set_hunter_force(enabled),
+            // The recorded time was scaled already
+            Input::Update(dt) => {
+                let result = self.advance(dt);
+                if result.is_ok() {
+                    self.record_snapshot();
+                }
+                return result;
+            }
+        }
+        Ok(())
+    }
+
+    /// Position will be clamped into the playing field. With inertial movement, it is
+    /// the target the hunter moves towards during the following updates.
     pub fn set_hunter_pos(&mut self, pos: Position) {
         if let Some(ref mut s) = self.state {
+            self.replay.inputs.push(Input::HunterPos(pos));
+            s.hunter.target = pos;
+            if self.movement != Movement::Direct {
+                return;
+            }
             s.hunter.velocity = vec2_scale(vec2_sub(pos, s.hunter.object.pos), 1.0 / s.last_dt);
             s.hunter.object.pos = pos;
 
@@ -319,6 +1053,7 @@ impl Engine {
     /// spheres.
     pub fn set_hunter_force(&mut self, enabled: bool) {
         if let Some(ref mut s) = self.state {
+            self.replay.inputs.push(Input::HunterForce(enabled));
             if enabled {
                 s.hunter.force = HUNTER_FORCE;
                 s.hunter.object.half_size = Self::hunter_half_size(&s.field) *
@@ -330,3 +1065,483 @@ impl Engine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Event, GameMode, ObstacleKind, Obstacle, Object, Physics, Movement, Spring};
+    use types::CollisionShape::Circle;
+    use replay::Replay;
+    use level::Level;
+
+    fn engine(mode: GameMode) -> Engine {
+        let mut replay = Replay::new(3, [800.0, 600.0]);
+        replay.mode = mode;
+        Engine::from_replay(&replay)
+    }
+
+    /// Keeps the hunter on the prey, returning the last state if the game ended
+    fn chase_prey(e: &mut Engine, seconds: f64) -> Option<State> {
+        for _ in 0..(seconds * 10.0) as usize {
+            let prey = e.state().as_ref().unwrap().prey.pos;
+            e.set_hunter_pos(prey);
+            if let Err(state) = e.update(0.1) {
+                return Some(state);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn modes() {
+        let mut e = engine(GameMode::Zen);
+        assert_eq!(e.mode(), GameMode::Zen);
+        assert_eq!(chase_prey(&mut e, 100.0), None);
+        let s = e.state().as_ref().unwrap();
+        assert!(s.score > 0);
+        assert!(s.obstacles.iter().all(|o| o.kind != ObstacleKind::Deadly));
+
+        // Staying out of the field avoids all obstacles, so only the time ends the game
+        let mut e = engine(GameMode::TimeAttack);
+        for _ in 0..599 {
+            assert_eq!(e.update(0.1), Ok(()));
+        }
+        let s = e.update(0.2).unwrap_err();
+        assert_eq!(e.events(), &[Event::TimeUp]);
+        assert_eq!(s.mode, GameMode::TimeAttack);
+
+        // Survival scores for time alive, while obstacles keep coming
+        let mut e = engine(GameMode::Survival);
+        for _ in 0..100 {
+            e.update(0.1).unwrap();
+        }
+        let s = e.state().as_ref().unwrap();
+        assert!(s.score >= 9 * SURVIVAL_SCORE_PER_SECOND);
+        assert!(s.obstacles.len() >= 2);
+
+        let mut e = engine(GameMode::Classic);
+        assert!(chase_prey(&mut e, 100.0).is_some());
+    }
+
+    #[test]
+    fn campaign() {
+        let level = Level::parse(r#"
+            [[wave]]
+            catch = 2
+            [[wave.spawn]]
+            kind = "invisibility_switch"
+            on_prey = true
+            [[wave]]
+            survive = 1.0
+            [[wave.obstacle]]
+            kind = "attractive_force_switch"
+            pos = [0.5, 0.5]
+            [[wave.spawn]]
+            kind = "attractive_force_switch"
+            every = 0.3
+            count = 2
+        "#)
+            .unwrap();
+        let mut e = engine(GameMode::Classic);
+        e.start_campaign([800.0, 600.0], vec![level]);
+        assert!(e.state().as_ref().unwrap().obstacles.is_empty());
+
+        let mut caught = 0;
+        while e.events().iter().all(|ev| *ev != Event::WaveCleared { level: 0, wave: 0 }) {
+            let s = e.state().clone().unwrap();
+            assert_eq!(s.obstacles.len(), caught);
+            e.set_hunter_pos(s.prey.pos);
+            e.update(0.1).unwrap();
+            caught = e.state().as_ref().unwrap().wave.as_ref().unwrap().caught as usize;
+        }
+        let s = e.state().clone().unwrap();
+        assert_eq!(s.obstacles.len(), 1);
+        assert_eq!(s.wave.as_ref().map(|w| (w.level, w.wave)), Some((0, 1)));
+
+        // Waiting outside of the field lets the time win the last wave
+        e.set_hunter_pos([-100.0, -100.0]);
+        for _ in 0..9 {
+            e.update(0.1).unwrap();
+        }
+        assert_eq!(e.state().as_ref().unwrap().obstacles.len(), 3);
+        assert!(e.update(0.2).is_err());
+        assert_eq!(e.events(),
+                   &[Event::WaveCleared { level: 0, wave: 1 }, Event::CampaignComplete]);
+    }
+
+    #[test]
+    fn wave_obstacles_keep_clear_of_hunter() {
+        let level = Level::parse(r#"
+            [[wave]]
+            survive = 0.5
+            [[wave]]
+            survive = 10
+            [[wave.obstacle]]
+            kind = "deadly"
+            pos = [0.5, 0.5]
+        "#)
+            .unwrap();
+        let mut e = engine(GameMode::Classic);
+        e.start_campaign([800.0, 600.0], vec![level]);
+        e.set_hunter_pos([400.0, 300.0]);
+        for _ in 0..6 {
+            e.update(0.1).unwrap();
+        }
+        let s = e.state().clone().unwrap();
+        assert_eq!(s.wave.as_ref().map(|w| w.wave), Some(1));
+        let distance = vec2_len(vec2_sub(s.obstacles[0].object.pos, s.hunter.object.pos));
+        assert!(distance >= 100.0, "{}", distance);
+    }
+
+    #[test]
+    fn obstacle_limits() {
+        // Survival spawns an obstacle every few seconds while the hunter waits outside
+        let mut e = engine(GameMode::Survival);
+        e.set_obstacle_limits(ObstacleLimits {
+            lifetime: Some(10.0),
+            max_count: Some(2),
+            recycle: RecycleStrategy::Oldest,
+        });
+        let mut despawned = 0;
+        for _ in 0..200 {
+            e.update(0.1).unwrap();
+            despawned += e.events()
+                .iter()
+                .filter(|ev| match **ev {
+                    Event::ObstacleDespawned { .. } => true,
+                    _ => false,
+                })
+                .count();
+            let s = e.state().as_ref().unwrap();
+            assert!(s.obstacles.iter().filter(|o| !o.is_departing()).count() <= 2);
+            assert!(s.obstacles.iter().all(|o| o.age <= 10.0 + 1e-9));
+        }
+        assert!(despawned >= 2);
+
+        // Only the two newest obstacles stay
+        let s = e.state().as_ref().unwrap();
+        assert!(s.obstacles
+            .iter()
+            .filter(|o| !o.is_departing())
+            .all(|o| o.age < SURVIVAL_OBSTACLE_INTERVAL * 2.0));
+
+        let mut obstacle = s.obstacles[0].clone();
+        obstacle.age = 0.25;
+        assert_eq!(obstacle.opacity(), 0.5);
+        obstacle.age = 5.0;
+        assert_eq!(obstacle.opacity(), 1.0);
+        obstacle.leave();
+        assert!(obstacle.is_leaving());
+        obstacle.age = 5.25;
+        assert_eq!(obstacle.opacity(), 0.5);
+    }
+
+    #[test]
+    fn fading_obstacles() {
+        let mut e = engine(GameMode::Classic);
+        let mut s = e.state().clone().unwrap();
+        let pos = [400.0, 300.0];
+        s.hunter.object.pos = pos;
+        let mut obstacle = Obstacle {
+            kind: ObstacleKind::Deadly,
+            object: Object {
+                pos: vec2_add(pos, [2.0, 0.0]),
+                half_size: 3.0,
+                shape: Circle,
+            },
+            velocity: [0.0, 0.0],
+            age: 0.0,
+            lifetime: Some(10.0),
+            mass: 1.0,
+            base_speed: 0.0,
+            departing: false,
+        };
+
+        // Leaving obstacles are harmless, new ones are deadly right away
+        obstacle.age = 9.8;
+        s.obstacles.push(obstacle.clone());
+        e.set_state(s.clone());
+        e.set_hunter_pos(pos);
+        assert!(e.update(0.01).is_ok());
+
+        obstacle.age = 0.0;
+        s.obstacles = vec![obstacle.clone()];
+        e.set_state(s);
+        e.set_hunter_pos(pos);
+        assert!(e.update(0.01).is_err());
+        assert!(e.events().iter().any(|ev| match *ev {
+            Event::GameOver { .. } => true,
+            _ => false,
+        }));
+
+        // Obstacles told to leave while fading in still disappear
+        obstacle.age = 0.1;
+        obstacle.leave();
+        obstacle.leave();
+        assert_eq!(obstacle.lifetime, Some(1.0));
+        assert!(obstacle.is_departing() && !obstacle.is_leaving());
+        obstacle.age = 0.8;
+        assert!(obstacle.is_leaving());
+    }
+
+    #[test]
+    fn recycle_fresh_obstacles() {
+        let mut e = engine(GameMode::Classic);
+        e.set_obstacle_limits(ObstacleLimits {
+            lifetime: None,
+            max_count: Some(2),
+            recycle: RecycleStrategy::Farthest,
+        });
+        let mut s = e.state().clone().unwrap();
+        s.obstacles = (1..5)
+            .map(|i| {
+                Obstacle {
+                    kind: ObstacleKind::Deadly,
+                    object: Object {
+                        pos: [i as Scalar * 100.0, 0.0],
+                        half_size: 3.0,
+                        shape: Circle,
+                    },
+                    velocity: [0.0, 0.0],
+                    age: 0.0,
+                    lifetime: None,
+                    mass: 1.0,
+                    base_speed: 0.0,
+                    departing: false,
+                }
+            })
+            .collect();
+        e.set_state(s);
+
+        // The two farthest obstacles leave while fading in, and stay the only ones
+        for _ in 0..5 {
+            e.set_hunter_pos([-100.0, 0.0]);
+            e.update(0.1).unwrap();
+            let departing: Vec<bool> = e.state()
+                .as_ref()
+                .unwrap()
+                .obstacles
+                .iter()
+                .map(|o| o.is_departing())
+                .collect();
+            assert_eq!(departing, vec![false, false, true, true]);
+        }
+    }
+
+    #[test]
+    fn combos() {
+        let mut e = engine(GameMode::Zen);
+        let catch = |e: &mut Engine| {
+            let prey = e.state().as_ref().unwrap().prey.pos;
+            e.set_hunter_pos(prey);
+            e.update(0.1).unwrap();
+            match e.events()[0] {
+                Event::PreyCaught { score, .. } => score,
+                ref event => panic!("unexpected {:?}", event),
+            }
+        };
+        let scores: Vec<u32> = (0..7).map(|_| catch(&mut e)).collect();
+        assert_eq!(e.state().as_ref().unwrap().combo, 7);
+        assert!(scores[1] > scores[0] * 3 / 2 && scores[2] > scores[1]);
+        assert!(scores[6] < scores[5] * 3 / 2);
+
+        e.set_hunter_pos([-100.0, -100.0]);
+        for _ in 0..(COMBO_WINDOW * 10.0) as usize + 1 {
+            e.update(0.1).unwrap();
+        }
+        let s = e.state().as_ref().unwrap();
+        assert_eq!((s.combo, s.combo_time_left), (0, 0.0));
+        assert!(catch(&mut e) < scores[1]);
+    }
+
+    #[test]
+    fn ledger() {
+        let mut e = engine(GameMode::Survival);
+        let s = match chase_prey(&mut e, 30.0) {
+            Some(s) => s,
+            None => e.state().clone().unwrap(),
+        };
+        assert!(s.ledger.iter().any(|entry| entry.source == ScoreSource::Prey));
+        assert!(s.ledger.iter().any(|entry| entry.source == ScoreSource::Survival));
+        assert_eq!(s.ledger.iter().map(|entry| entry.points).sum::<u32>(), s.score);
+
+        let mut last_time = 0.0;
+        for entry in &s.ledger {
+            assert!(entry.time >= last_time && entry.time <= s.time);
+            last_time = entry.time;
+            assert_eq!(entry.points, (entry.base * entry.multiplier()) as u32);
+            match entry.source {
+                ScoreSource::Prey => {
+                    assert_eq!(entry.base, SCORE_PER_PREY);
+                    assert!(entry.score_coeff >= 1.0);
+                    assert!(entry.combo >= 1 && entry.combo <= MAX_COMBO_MULTIPLIER);
+                }
+                ScoreSource::Survival => {
+                    assert_eq!(entry.base, SURVIVAL_SCORE_PER_SECOND as Scalar);
+                    assert_eq!(entry.multiplier(), 1.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inertial_movement() {
+        let mut replay = Replay::new(3, [800.0, 600.0]);
+        replay.movement = Movement::Inertial(Spring::default());
+        let mut e = Engine::from_replay(&replay);
+        assert_eq!(e.movement(), Movement::Inertial(Spring::default()));
+        let start = e.state().as_ref().unwrap().hunter.object.pos;
+
+        // The hunter lags behind its target, keeps moving without new input,
+        // and comes to rest there
+        e.set_hunter_pos([400.0, 300.0]);
+        assert_eq!(e.state().as_ref().unwrap().hunter.object.pos, start);
+        e.update(0.05).unwrap();
+        let hunter = e.state().as_ref().unwrap().hunter.clone();
+        assert!(hunter.object.pos != start && hunter.object.pos != [400.0, 300.0]);
+        assert!(vec2_len(hunter.velocity) > 0.0);
+        assert!(vec2_len(hunter.velocity) <= Spring::default().max_speed * 600.0 + 1e-9);
+        for _ in 0..50 {
+            e.update(0.05).unwrap();
+        }
+        let hunter = &e.state().as_ref().unwrap().hunter;
+        assert!(vec2_len(vec2_sub(hunter.object.pos, [400.0, 300.0])) < 1.0);
+
+        assert_eq!(Engine::from_replay(e.replay()).movement(), e.movement());
+    }
+
+    #[test]
+    fn physics() {
+        let mut s = engine(GameMode::Classic).state().clone().unwrap();
+        s.hunter.object.pos = [400.0, 300.0];
+        s.hunter.force = HUNTER_FORCE;
+        s.obstacles.push(Obstacle {
+            kind: ObstacleKind::Deadly,
+            object: Object {
+                pos: [420.0, 300.0],
+                half_size: 3.0,
+                shape: Circle,
+            },
+            velocity: [0.0, 0.0],
+            age: 0.0,
+            lifetime: None,
+            mass: 1.0,
+            base_speed: 0.0,
+            departing: false,
+        });
+        let pushed = |physics: &Physics, fps: usize| {
+            let mut s = s.clone();
+            for _ in 0..fps {
+                Engine::advect_obstacles(&mut s, physics, 1.0 / fps as f64);
+            }
+            s.obstacles[0].clone()
+        };
+
+        // The outcome hardly depends on the frame rate
+        let (slow, fast) = (pushed(&Physics::default(), 30), pushed(&Physics::default(), 240));
+        assert!(slow.object.pos[0] > 450.0);
+        assert!((slow.object.pos[0] - fast.object.pos[0]).abs() < 5.0);
+        assert!(vec2_len(vec2_sub(slow.velocity, fast.velocity)) < 5.0);
+
+        // Drag slows the obstacle down once it left the force field behind
+        let without_drag = pushed(&Physics {
+                                      drag: 0.0,
+                                      ..Physics::default()
+                                  },
+                                  60);
+        assert!(vec2_len(without_drag.velocity) > vec2_len(slow.velocity));
+
+        let limited = pushed(&Physics {
+                                 drag: 0.0,
+                                 max_speed: 0.01,
+                             },
+                             60);
+        assert!(vec2_len(limited.velocity) <= 6.0 + 1e-9);
+
+        let mut heavy = s.clone();
+        heavy.obstacles[0].mass = 2.0;
+        Engine::advect_obstacles(&mut heavy, &Physics::default(), 0.1);
+        let mut light = s.clone();
+        Engine::advect_obstacles(&mut light, &Physics::default(), 0.1);
+        assert!(vec2_len(heavy.obstacles[0].velocity) < vec2_len(light.obstacles[0].velocity));
+    }
+
+    #[test]
+    fn time_scale_and_steps() {
+        let mut e = engine(GameMode::Zen);
+        e.update(0.1).unwrap();
+        assert!(!e.is_practice());
+
+        // Slowed down games are practice, even once back at normal speed
+        e.reset([800.0, 600.0]);
+        e.set_time_scale(0.25);
+        e.update(0.1).unwrap();
+        assert!(e.is_practice());
+        assert_eq!(e.advanced(), 0.025);
+        assert_eq!(e.state().as_ref().unwrap().time, 0.025);
+
+        // In step mode, time only passes by whole steps on request
+        e.set_step_mode(true);
+        e.update(0.1).unwrap();
+        assert_eq!(e.advanced(), 0.0);
+        e.request_step();
+        e.update(0.1).unwrap();
+        e.update(0.1).unwrap();
+        assert_eq!(e.state().as_ref().unwrap().time, 0.025 + STEP_TIME);
+
+        // Replays hold the time which passed in the game, and play back the same
+        let replay = e.replay().clone();
+        assert_eq!(replay.duration(), 0.025 + STEP_TIME);
+        let mut simulated = None;
+        replay.simulate(|_, s| simulated = Some(s.clone()));
+        assert_eq!(simulated.as_ref(), e.state().as_ref());
+
+        // Only a new game is no practice anymore, while stepping makes one practice
+        e.set_time_scale(1.0);
+        e.set_step_mode(false);
+        e.update(0.1).unwrap();
+        assert!(e.is_practice());
+        e.reset([800.0, 600.0]);
+        assert!(!e.is_practice());
+        e.set_step_mode(true);
+        e.request_step();
+        e.update(0.1).unwrap();
+        assert!(e.is_practice());
+    }
+
+    #[test]
+    fn rewind() {
+        let mut e = engine(GameMode::Zen);
+        assert!(chase_prey(&mut e, 1.0).is_none());
+        assert_eq!(e.rewind(1.0), 0.0);
+
+        e.set_rewind_duration(2.0);
+        let mut states = Vec::new();
+        for _ in 0..3 {
+            assert!(chase_prey(&mut e, 1.0).is_none());
+            states.push(e.state().clone().unwrap());
+        }
+        let history = e.history();
+        assert!(history[0].time >= history[history.len() - 1].time - 2.0 - 1e-9);
+        assert!(history.iter().all(|s| s.ledger.is_empty()));
+        assert!(states[2].ledger.len() > states[1].ledger.len());
+
+        // Playing on from an earlier point is the same as if it never was left
+        assert!((e.rewind(1.0) - 1.0).abs() < 1e-9);
+        assert!(e.is_rewound());
+        assert_eq!(e.state().as_ref(), Some(&states[1]));
+        assert!(chase_prey(&mut e, 1.0).is_none());
+        assert_eq!(e.state().as_ref(), Some(&states[2]));
+
+        // The replay of a rewound game only holds what remained
+        let mut simulated = None;
+        e.replay().simulate(|_, s| simulated = Some(s.clone()));
+        assert_eq!(simulated.as_ref(), Some(&states[2]));
+
+        // There is no going back beyond the oldest state
+        assert!(e.rewind(100.0) < 2.0 + 1e-9);
+        assert_eq!(e.rewind(1.0), 0.0);
+        e.reset([800.0, 600.0]);
+        assert!(!e.is_rewound() && e.history().is_empty());
+    }
+}
@@ -0,0 +1,19 @@
+/// Parses lines of `key = value` pairs, ignoring empty lines and `#` comments.
+///
+/// Returns the line number of the first malformed line as error.
+pub fn parse_pairs(content: &str) -> Result<Vec<(String, String)>, usize> {
+    let mut pairs = Vec::new();
+    for (lid, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.find('=') {
+            Some(pos) => {
+                pairs.push((line[..pos].trim().to_string(), line[pos + 1..].trim().to_string()))
+            }
+            None => return Err(lid + 1),
+        }
+    }
+    Ok(pairs)
+}
@@ -0,0 +1,216 @@
+use types::{State, Hunter};
+use engine::Engine;
+use replay::{Replay, Input};
+
+fn update_dt(input: &Input) -> Option<f64> {
+    match *input {
+        Input::Update(dt) => Some(dt),
+        _ => None,
+    }
+}
+
+/// Re-enacts a recorded game alongside a live one, which should use the same seed
+/// for the games to be comparable.
+pub struct Ghost {
+    replay: Replay,
+    engine: Engine,
+    /// Index of the next input to apply
+    next_input: usize,
+    /// Time passed in the live game, in seconds
+    time: f64,
+    /// Time passed in the recorded game, which trails `time` by less than one update
+    ghost_time: f64,
+    /// The last state of the recorded game, once it is over
+    finished: Option<State>,
+}
+
+impl Ghost {
+    pub fn new(replay: Replay) -> Ghost {
+        Ghost {
+            engine: Engine::from_replay(&replay),
+            replay: replay,
+            next_input: 0,
+            time: 0.0,
+            ghost_time: 0.0,
+            finished: None,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// How far back `seek()` can go without playing the recorded game again from
+    /// the start, which should match the `Engine::rewind_duration()` of the live game
+    pub fn set_rewind_duration(&mut self, seconds: f64) {
+        self.engine.set_rewind_duration(seconds);
+    }
+
+    /// Start over, to race against the same game once more
+    pub fn restart(&mut self) {
+        self.engine.reset_with_seed(self.replay.field, self.replay.seed);
+        self.next_input = 0;
+        self.time = 0.0;
+        self.ghost_time = 0.0;
+        self.finished = None;
+    }
+
+    /// Advance the recorded game until it caught up with the live game,
+    /// which progressed by `dt` seconds
+    pub fn update(&mut self, dt: f64) {
+        self.time += dt;
+        let inputs = &self.replay.inputs;
+        while self.finished.is_none() && self.next_input < inputs.len() {
+            // Inputs are applied together with the update following them, as they happened
+            // during the same frame
+            let next_input = self.next_input;
+            let (end, ghost_dt) = inputs[next_input..]
+                .iter()
+                .enumerate()
+                .filter_map(|(offset, i)| update_dt(i).map(|dt| (next_input + offset + 1, dt)))
+                .next()
+                .unwrap_or((inputs.len(), 0.0));
+            if self.ghost_time + ghost_dt > self.time {
+                break;
+            }
+            self.ghost_time += ghost_dt;
+            for input in &inputs[self.next_input..end] {
+                if let Err(state) = self.engine.apply(input) {
+                    self.finished = Some(state);
+                    break;
+                }
+            }
+            self.next_input = end;
+        }
+    }
+
+    /// Move to the given time of the live game, which may lie in the past if it
+    /// was rewound. Going back rewinds the recorded game as well, or plays it again
+    /// from the start if it doesn't reach back that far.
+    pub fn seek(&mut self, time: f64) {
+        if time < self.ghost_time && !self.rewind_to(time) {
+            self.restart();
+        }
+        let dt = time - self.time;
+        self.update(dt);
+    }
+
+    /// Rewind the recorded game to the latest state it kept at or before `time`.
+    /// Returns false if there is none.
+    fn rewind_to(&mut self, time: f64) -> bool {
+        if let Some(state) = self.finished.take() {
+            self.engine.resume(state);
+        }
+        self.engine.rewind(self.ghost_time - time);
+        let rewound_time = match *self.engine.state() {
+            Some(ref s) if s.time <= time => s.time,
+            _ => return false,
+        };
+        self.ghost_time = rewound_time;
+        self.next_input = self.engine.replay().inputs.len();
+        true
+    }
+
+    /// The state of the recorded game at the current time
+    pub fn state(&self) -> Option<&State> {
+        self.finished.as_ref().or(self.engine.state().as_ref())
+    }
+
+    pub fn hunter(&self) -> Option<&Hunter> {
+        self.state().map(|s| &s.hunter)
+    }
+
+    pub fn score(&self) -> u32 {
+        self.state().map(|s| s.score).unwrap_or(0)
+    }
+
+    /// True once the recorded game is over, or all of its input was used
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some() || self.next_input == self.replay.inputs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Scalar;
+    use engine::Engine;
+
+    #[test]
+    fn follows_recorded_game() {
+        let steer = |engine: &mut Engine, step: usize| {
+            let t = step as Scalar / 60.0;
+            engine.set_hunter_pos([400.0 + 250.0 * (t * 0.7).cos(), 300.0 + 150.0 * t.sin()]);
+            engine.set_hunter_force(step % 100 > 70);
+            engine.update(1.0 / 60.0).is_ok()
+        };
+        let mut recorded = Engine::from_seed([800.0, 600.0], 7);
+        let mut steps = 0;
+        while steps < 300 && steer(&mut recorded, steps) {
+            steps += 1;
+        }
+
+        // Playing the same way with the same seed keeps the ghost right on the hunter
+        let mut ghost = Ghost::new(recorded.replay().clone());
+        ghost.set_rewind_duration(1.0);
+        let mut live = Engine::from_seed([800.0, 600.0], 7);
+        let mut states = Vec::new();
+        for step in 0..steps {
+            let running = steer(&mut live, step);
+            ghost.update(1.0 / 60.0);
+            assert!(running);
+            let s = live.state().clone().unwrap();
+            assert_eq!(ghost.hunter(), Some(&s.hunter));
+            assert_eq!(ghost.score(), s.score);
+            states.push(s);
+        }
+
+        // A rewound live game takes the ghost back as well, without playing it again
+        // unless it goes back further than the ghost kept
+        let score = ghost.score();
+        let recent = &states[steps - 30];
+        ghost.seek(recent.time);
+        assert!(ghost.engine.is_rewound());
+        assert_eq!(ghost.hunter(), Some(&recent.hunter));
+        ghost.seek(states[59].time);
+        assert!(!ghost.engine.is_rewound());
+        assert_eq!(ghost.hunter(), Some(&states[59].hunter));
+        ghost.seek(steps as f64 / 60.0);
+        assert_eq!(ghost.score(), score);
+
+        ghost.update(1000.0);
+        assert!(ghost.is_finished());
+        let recent = &states[steps - 10];
+        ghost.seek(recent.time);
+        assert!(ghost.engine.is_rewound() && !ghost.is_finished());
+        assert_eq!(ghost.hunter(), Some(&recent.hunter));
+        ghost.restart();
+        assert!(!ghost.is_finished());
+        assert_eq!(ghost.score(), 0);
+    }
+
+    #[test]
+    fn rewinds_finished_game() {
+        let mut recorded = Engine::from_seed([800.0, 600.0], 1);
+        let mut step = 0;
+        let last = loop {
+            let t = step as Scalar / 60.0;
+            recorded.set_hunter_pos([400.0 + 300.0 * t.cos(), 300.0 + 200.0 * (t * 1.3).sin()]);
+            if let Err(state) = recorded.update(1.0 / 60.0) {
+                break state;
+            }
+            step += 1;
+        };
+
+        // A ghost whose game is over comes back when the live game is rewound
+        let mut ghost = Ghost::new(recorded.replay().clone());
+        ghost.set_rewind_duration(1.0);
+        ghost.update(last.time + 1.0);
+        assert_eq!(ghost.state(), Some(&last));
+        ghost.seek(last.time - 0.5);
+        assert!(ghost.engine.is_rewound() && !ghost.is_finished());
+        assert!(ghost.state().unwrap().time <= last.time - 0.5);
+        ghost.seek(last.time + 1.0);
+        assert_eq!(ghost.state(), Some(&last));
+    }
+}
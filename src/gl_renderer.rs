@@ -0,0 +1,66 @@
+use catchit::{Renderer, Color, Position};
+
+use opengl_graphics::glyph_cache::GlyphCache;
+use opengl_graphics::GlGraphics;
+use graphics::{self, rectangle, ellipse, Context, Transformed, Text, Line};
+use graphics::character::CharacterCache;
+use graphics::math::Scalar;
+
+use screen::Screen;
+use FONT_SIZE;
+
+/// Draws into the window using OpenGL, within a context that is already
+/// transformed into logical points
+pub struct GlRenderer<'a> {
+    pub c: Context,
+    pub gl: &'a mut GlGraphics,
+    pub font: &'a mut GlyphCache<'static>,
+    pub screen: &'a Screen,
+    /// Height of capital letters in logical points
+    pub text_height: Scalar,
+}
+
+impl<'a> Renderer for GlRenderer<'a> {
+    fn clear(&mut self, color: Color) {
+        graphics::clear(color, self.gl);
+    }
+
+    fn rectangle(&mut self, rect: [Scalar; 4], color: Color) {
+        rectangle(color, rect, self.c.transform, self.gl);
+    }
+
+    fn ellipse(&mut self, rect: [Scalar; 4], color: Color) {
+        ellipse(color, rect, self.c.transform, self.gl);
+    }
+
+    fn line(&mut self, from: Position, to: Position, width: Scalar, color: Color) {
+        Line::new(color, width / 2.0).draw([from[0], from[1], to[0], to[1]],
+                                           &self.c.draw_state,
+                                           self.c.transform,
+                                           self.gl);
+    }
+
+    /// Glyphs are rasterized at the display's pixel density and scaled down into
+    /// logical space, which keeps them sharp on HiDPI displays.
+    fn text(&mut self, text: &str, pos: Position, color: Color) {
+        let size = self.screen.font_size(FONT_SIZE);
+        let transform = self.c
+            .transform
+            .trans(pos[0], pos[1])
+            .zoom(FONT_SIZE as Scalar / size as Scalar);
+        Text::new_color(color, size).draw(text, self.font, &self.c.draw_state, transform, self.gl);
+    }
+
+    fn text_width(&mut self, text: &str) -> Scalar {
+        let size = self.screen.font_size(FONT_SIZE);
+        let mut w = 0.0;
+        for ch in text.chars() {
+            w += self.font.character(size, ch).width();
+        }
+        w * FONT_SIZE as Scalar / size as Scalar
+    }
+
+    fn text_height(&self) -> Scalar {
+        self.text_height
+    }
+}
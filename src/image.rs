@@ -0,0 +1,168 @@
+use theme::Color;
+use deflate::zlib;
+#[cfg(test)]
+use deflate::unzlib;
+
+/// An RGBA image with 8 bits per channel, stored row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+fn to_byte(v: f32) -> u8 {
+    (v.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+impl Image {
+    /// A fully transparent image of the given size
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width: width,
+            height: height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    pub fn fill(&mut self, color: Color) {
+        let c = [to_byte(color[0]), to_byte(color[1]), to_byte(color[2]), to_byte(color[3])];
+        for p in self.pixels.chunks_mut(4) {
+            p.copy_from_slice(&c);
+        }
+    }
+
+    /// Draw `color` over the pixel at `x`, `y`, respecting its alpha.
+    /// Pixels outside of the image are ignored.
+    pub fn blend(&mut self, x: i64, y: i64, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = color[3].max(0.0).min(1.0);
+        for (pixel, &channel) in self.pixels[i..i + 3].iter_mut().zip(&color[..3]) {
+            let dst = *pixel as f32 / 255.0;
+            *pixel = to_byte(channel * alpha + dst * (1.0 - alpha));
+        }
+        let dst = self.pixels[i + 3] as f32 / 255.0;
+        self.pixels[i + 3] = to_byte(alpha + dst * (1.0 - alpha));
+    }
+
+    /// Encode the image as PNG, which is byte-for-byte reproducible
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut out = png_header(self.width, self.height);
+        push_chunk(&mut out, b"IDAT", &png_data(self));
+        push_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+}
+
+/// The compressed pixels of `img`, as stored in PNG data chunks
+pub fn png_data(img: &Image) -> Vec<u8> {
+    let row_len = img.width as usize * 4;
+    let mut raw = Vec::with_capacity((row_len + 1) * img.height as usize);
+    for row in img.pixels.chunks(row_len.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    zlib(&raw)
+}
+
+pub fn png_header(width: u32, height: u32) -> Vec<u8> {
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    let mut header = Vec::with_capacity(13);
+    push_u32(&mut header, width);
+    push_u32(&mut header, height);
+    // 8 bits per channel, RGBA, deflate, no filtering, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    push_chunk(&mut out, b"IHDR", &header);
+    out
+}
+
+pub fn push_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&[(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, v as u8]);
+}
+
+pub fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    push_u32(out, data.len() as u32);
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    push_u32(out, crc);
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Reads back the pixels of a PNG written by `to_png()`, which are unfiltered
+/// RGBA in any amount of data chunks
+#[cfg(test)]
+pub fn decode_png(png: &[u8]) -> Image {
+    let be32 = |b: &[u8]| {
+        (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+    };
+    let (mut width, mut height, mut data) = (0, 0, Vec::new());
+    let mut pos = 8;
+    while pos < png.len() {
+        let len = be32(&png[pos..]) as usize;
+        let body = &png[pos + 8..pos + 8 + len];
+        match &png[pos + 4..pos + 8] {
+            b"IHDR" => {
+                width = be32(body);
+                height = be32(&body[4..]);
+                assert_eq!(&body[8..], &[8, 6, 0, 0, 0]);
+            }
+            b"IDAT" => data.extend_from_slice(body),
+            _ => {}
+        }
+        pos += 12 + len;
+    }
+    let raw = unzlib(&data);
+    let row_len = width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_len * height as usize);
+    for row in raw.chunks(row_len + 1) {
+        assert_eq!(row[0], 0, "Only unfiltered rows are supported");
+        pixels.extend_from_slice(&row[1..]);
+    }
+    Image {
+        width: width,
+        height: height,
+        pixels: pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+
+        let mut img = Image::new(2, 1);
+        img.fill([1.0, 0.0, 0.0, 1.0]);
+        img.blend(1, 0, [0.0, 0.0, 1.0, 0.5]);
+        img.blend(5, 5, [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(img.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(img.pixel(1, 0), [128, 0, 128, 255]);
+
+        let png = img.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xaeB`\x82");
+        assert_eq!(decode_png(&png), img);
+    }
+}
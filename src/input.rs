@@ -0,0 +1,283 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use piston_window::{Button, Key, MouseButton};
+
+use catchit::parse_pairs;
+
+use config;
+
+/// Everything the player can do, independently of the button triggering it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    NewGame,
+    Force,
+    Pause,
+    Quit,
+    EditBindings,
+    Screenshot,
+    DebugOverlay,
+    SlowMotion,
+    Freeze,
+    Step,
+    Rewind,
+}
+
+const ACTIONS: &'static [Action] = &[Action::NewGame,
+                                     Action::Force,
+                                     Action::Pause,
+                                     Action::Quit,
+                                     Action::EditBindings,
+                                     Action::Screenshot,
+                                     Action::DebugOverlay,
+                                     Action::SlowMotion,
+                                     Action::Freeze,
+                                     Action::Step,
+                                     Action::Rewind];
+
+/// Keys we know how to name in the bindings file
+const KEYS: &'static [Key] = &[Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H,
+                               Key::I, Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P,
+                               Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X,
+                               Key::Y, Key::Z, Key::D0, Key::D1, Key::D2, Key::D3, Key::D4,
+                               Key::D5, Key::D6, Key::D7, Key::D8, Key::D9, Key::F1, Key::F2,
+                               Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9,
+                               Key::F10, Key::F11, Key::F12, Key::Space, Key::Return, Key::Tab,
+                               Key::Escape, Key::Backspace, Key::Delete, Key::Up, Key::Down,
+                               Key::Left, Key::Right, Key::LShift, Key::RShift, Key::LCtrl,
+                               Key::RCtrl, Key::LAlt, Key::RAlt, Key::Comma, Key::Period,
+                               Key::Minus, Key::Equals, Key::Slash];
+
+const MOUSE_BUTTONS: &'static [MouseButton] = &[MouseButton::Left,
+                                                MouseButton::Right,
+                                                MouseButton::Middle];
+
+impl Action {
+    pub fn all() -> &'static [Action] {
+        ACTIONS
+    }
+
+    /// The name used in the bindings file
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Action::NewGame => "new_game",
+            Action::Force => "force",
+            Action::Pause => "pause",
+            Action::Quit => "quit",
+            Action::EditBindings => "edit_bindings",
+            Action::Screenshot => "screenshot",
+            Action::DebugOverlay => "debug_overlay",
+            Action::SlowMotion => "slow_motion",
+            Action::Freeze => "freeze",
+            Action::Step => "step",
+            Action::Rewind => "rewind",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().cloned().find(|a| a.name() == name)
+    }
+
+    /// Human readable text, as displayed on screen
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Action::NewGame => "New game",
+            Action::Force => "Repelling force",
+            Action::Pause => "Pause",
+            Action::Quit => "Quit",
+            Action::EditBindings => "Edit bindings",
+            Action::Screenshot => "Screenshot",
+            Action::DebugOverlay => "Debug overlay",
+            Action::SlowMotion => "Slow motion",
+            Action::Freeze => "Freeze",
+            Action::Step => "Single step",
+            Action::Rewind => "Rewind",
+        }
+    }
+}
+
+pub fn button_name(button: &Button) -> String {
+    match *button {
+        Button::Keyboard(ref k) => format!("{:?}", k),
+        Button::Mouse(ref b) => format!("Mouse{:?}", b),
+    }
+}
+
+pub fn button_from_name(name: &str) -> Option<Button> {
+    if name.starts_with("Mouse") {
+        MOUSE_BUTTONS.iter()
+            .find(|b| format!("{:?}", b) == &name["Mouse".len()..])
+            .map(|b| Button::Mouse(*b))
+    } else {
+        KEYS.iter().find(|k| format!("{:?}", k) == name).map(|k| Button::Keyboard(*k))
+    }
+}
+
+/// Maps buttons to actions. Each action may have any amount of buttons, but a
+/// button triggers at most one action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    map: Vec<(Action, Vec<Button>)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings {
+            map: vec![(Action::NewGame, vec![Button::Keyboard(Key::Space)]),
+                      (Action::Force, vec![Button::Mouse(MouseButton::Left)]),
+                      (Action::Pause, vec![Button::Keyboard(Key::P)]),
+                      (Action::Quit, vec![Button::Keyboard(Key::Escape)]),
+                      (Action::EditBindings, vec![Button::Keyboard(Key::F1)]),
+                      (Action::Screenshot, vec![Button::Keyboard(Key::F12)]),
+                      (Action::DebugOverlay, vec![Button::Keyboard(Key::F3)]),
+                      (Action::SlowMotion, vec![Button::Keyboard(Key::F5)]),
+                      (Action::Freeze, vec![Button::Keyboard(Key::F6)]),
+                      (Action::Step, vec![Button::Keyboard(Key::F7)]),
+                      (Action::Rewind, vec![Button::Keyboard(Key::R)])],
+        }
+    }
+}
+
+impl Bindings {
+    /// Load bindings from the given file, or use the defaults if it cannot be
+    /// read. Actions not mentioned in the file keep their default buttons.
+    pub fn load(path: &Path) -> Bindings {
+        match config::read_file(path) {
+            Ok(content) => {
+                Bindings::parse(&content).unwrap_or_else(|err| {
+                    println!("Ignoring bindings at '{}': {}", path.display(), err);
+                    Bindings::default()
+                })
+            }
+            Err(_) => Bindings::default(),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Bindings, String> {
+        let pairs = try!(parse_pairs(content)
+            .map_err(|line| format!("Line {}: expected 'action = button, ...'", line)));
+        let mut bindings = Bindings::default();
+        for (name, buttons) in pairs {
+            let action = try!(Action::from_name(&name)
+                .ok_or_else(|| format!("Unknown action: '{}'", name)));
+            bindings.clear(action);
+            for bname in buttons.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                let button = try!(button_from_name(bname)
+                    .ok_or_else(|| format!("Unknown button: '{}'", bname)));
+                bindings.bind(action, button);
+            }
+        }
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &Path) -> ::std::io::Result<()> {
+        config::write_file(path, self.to_config().as_bytes())
+    }
+
+    /// Serialize the bindings in the format understood by `parse()`
+    pub fn to_config(&self) -> String {
+        let mut out = String::from("# catchit key bindings: action = button, ...\n");
+        for &(action, ref buttons) in &self.map {
+            let names: Vec<_> = buttons.iter().map(button_name).collect();
+            writeln!(out, "{} = {}", action.name(), names.join(", ")).unwrap();
+        }
+        out
+    }
+
+    /// Returns the action triggered by `button`, if there is any
+    pub fn action(&self, button: &Button) -> Option<Action> {
+        self.map.iter().find(|&&(_, ref buttons)| buttons.contains(button)).map(|&(a, _)| a)
+    }
+
+    pub fn buttons(&self, action: Action) -> &[Button] {
+        self.map
+            .iter()
+            .find(|&&(a, _)| a == action)
+            .map(|&(_, ref buttons)| &buttons[..])
+            .unwrap_or(&[])
+    }
+
+    /// A short text listing all buttons for the given action, like `Space or Return`
+    pub fn describe(&self, action: Action) -> String {
+        let names: Vec<_> = self.buttons(action).iter().map(button_name).collect();
+        if names.is_empty() {
+            "<unbound>".to_string()
+        } else {
+            names.join(" or ")
+        }
+    }
+
+    /// Adds `button` to the buttons of `action`, and removes it from all other actions.
+    pub fn bind(&mut self, action: Action, button: Button) {
+        for &mut (a, ref mut buttons) in &mut self.map {
+            if a == action {
+                if !buttons.contains(&button) {
+                    buttons.push(button);
+                }
+            } else {
+                buttons.retain(|b| *b != button);
+            }
+        }
+    }
+
+    /// Removes all buttons from the given action
+    pub fn clear(&mut self, action: Action) {
+        for &mut (a, ref mut buttons) in &mut self.map {
+            if a == action {
+                buttons.clear();
+            }
+        }
+    }
+}
+
+/// State of the screen allowing to change bindings in-game.
+///
+/// Navigation within it is fixed, to prevent the player from locking themselves
+/// out: Up/Down select an action, Return adds the next pressed button to it,
+/// Backspace clears it, and Escape leaves the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingsEditor {
+    pub selected: usize,
+    pub capturing: bool,
+}
+
+/// What the bindings editor wants to happen after handling a button press
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorResult {
+    Continue,
+    Close,
+}
+
+impl BindingsEditor {
+    pub fn new() -> BindingsEditor {
+        BindingsEditor {
+            selected: 0,
+            capturing: false,
+        }
+    }
+
+    pub fn on_press(&mut self, button: Button, bindings: &mut Bindings) -> EditorResult {
+        let action = ACTIONS[self.selected];
+        if self.capturing {
+            self.capturing = false;
+            if button != Button::Keyboard(Key::Escape) {
+                bindings.bind(action, button);
+            }
+            return EditorResult::Continue;
+        }
+
+        match button {
+            Button::Keyboard(Key::Up) => {
+                self.selected = (self.selected + ACTIONS.len() - 1) % ACTIONS.len();
+            }
+            Button::Keyboard(Key::Down) => {
+                self.selected = (self.selected + 1) % ACTIONS.len();
+            }
+            Button::Keyboard(Key::Return) => self.capturing = true,
+            Button::Keyboard(Key::Backspace) => bindings.clear(action),
+            Button::Keyboard(Key::Escape) => return EditorResult::Close,
+            _ => {}
+        }
+        EditorResult::Continue
+    }
+}
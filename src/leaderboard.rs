@@ -0,0 +1,261 @@
+//! A leaderboard of verified scores, stored in flat files. `catchit-leaderboard`
+//! serves it over HTTP.
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use types::GameMode;
+use replay::{Replay, VerifiedScore, verify_replay};
+
+const SCORES_FILE_NAME: &'static str = "scores.txt";
+const REPLAYS_DIR_NAME: &'static str = "replays";
+const MAX_NAME_LEN: usize = 24;
+
+/// A verified score on the leaderboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    /// Identifies the entry and its replay, in the order they were submitted
+    pub id: u64,
+    pub name: String,
+    pub mode: GameMode,
+    pub seed: u64,
+    pub score: u32,
+    pub ticks: usize,
+}
+
+impl LeaderboardEntry {
+    fn parse(line: &str) -> Option<LeaderboardEntry> {
+        let t: Vec<&str> = line.split_whitespace().collect();
+        if t.len() != 6 {
+            return None;
+        }
+        match (t[0].parse(), GameMode::from_name(t[2]), t[3].parse(), t[4].parse(), t[5].parse()) {
+            (Ok(id), Some(mode), Ok(seed), Ok(score), Ok(ticks)) => {
+                Some(LeaderboardEntry {
+                    id: id,
+                    name: t[1].to_string(),
+                    mode: mode,
+                    seed: seed,
+                    score: score,
+                    ticks: ticks,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        format!("{} {} {} {} {} {}",
+                self.id,
+                self.name,
+                self.mode.name(),
+                self.seed,
+                self.score,
+                self.ticks)
+    }
+}
+
+/// Names are stored in whitespace separated lines
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_NAME_LEN &&
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Identifies a replay by its text, using 64 bit FNV-1a
+fn replay_hash(text: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for b in text.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// A game submitted under some name, whose replay was played again and ended as
+/// it claims
+#[derive(Debug, Clone)]
+pub struct Submission {
+    name: String,
+    replay: Replay,
+    score: VerifiedScore,
+}
+
+impl Submission {
+    /// Verifies the game recorded by the given replay. This plays the whole game
+    /// again, and doesn't need the leaderboard it is submitted to.
+    pub fn verify(name: &str, replay_text: &str) -> Result<Submission, String> {
+        if !is_valid_name(name) {
+            return Err(format!("Names consist of up to {} letters, digits, '-' and '_'",
+                               MAX_NAME_LEN));
+        }
+        let replay = try!(Replay::parse(replay_text));
+        let score = try!(verify_replay(&replay).map_err(|e| e.to_string()));
+        Ok(Submission {
+            name: name.to_string(),
+            replay: replay,
+            score: score,
+        })
+    }
+}
+
+/// All scores ever submitted, backed by a directory holding a line per score in
+/// `scores.txt`, and the replays of all of them. Every replay is accepted once.
+#[derive(Debug)]
+pub struct Leaderboard {
+    dir: PathBuf,
+    entries: Vec<LeaderboardEntry>,
+    /// The `replay_hash` of every stored replay
+    replays: HashSet<u64>,
+}
+
+impl Leaderboard {
+    /// Opens the leaderboard stored in `dir`, which is created if needed.
+    /// Malformed lines are ignored.
+    pub fn open<P: Into<PathBuf>>(dir: P) -> io::Result<Leaderboard> {
+        let dir = dir.into();
+        try!(fs::create_dir_all(dir.join(REPLAYS_DIR_NAME)));
+        let mut content = String::new();
+        match fs::File::open(dir.join(SCORES_FILE_NAME)) {
+            Ok(mut f) => {
+                try!(f.read_to_string(&mut content));
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        let mut board = Leaderboard {
+            dir: dir,
+            entries: content.lines().filter_map(LeaderboardEntry::parse).collect(),
+            replays: HashSet::new(),
+        };
+        board.replays = board.entries
+            .iter()
+            .filter_map(|e| board.replay_text(e.id))
+            .map(|text| replay_hash(&text))
+            .collect();
+        Ok(board)
+    }
+
+    /// Stores the verified game, unless its replay was submitted before
+    pub fn submit(&mut self, submission: Submission) -> Result<&LeaderboardEntry, String> {
+        let Submission { name, replay, score } = submission;
+        let text = replay.to_text();
+        let hash = replay_hash(&text);
+        if self.replays.contains(&hash) {
+            return Err("This game was submitted before".to_string());
+        }
+        let entry = LeaderboardEntry {
+            id: self.entries.iter().map(|e| e.id).max().unwrap_or(0) + 1,
+            name: name,
+            mode: replay.mode,
+            seed: replay.seed,
+            score: score.score,
+            ticks: score.ticks,
+        };
+
+        // The replay goes first, so every listed score has one
+        let io_err = |e: io::Error| format!("Could not store the score: {}", e);
+        try!(fs::File::create(self.replay_path(entry.id))
+            .and_then(|mut f| f.write_all(text.as_bytes()))
+            .map_err(&io_err));
+        try!(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(SCORES_FILE_NAME))
+            .and_then(|mut f| writeln!(f, "{}", entry.to_line()))
+            .map_err(&io_err));
+        self.replays.insert(hash);
+        self.entries.push(entry);
+        Ok(self.entries.last().unwrap())
+    }
+
+    /// The best scores matching the given filters, best one first. Equal scores
+    /// are ordered by who got them first.
+    pub fn query(&self, mode: Option<GameMode>, seed: Option<u64>) -> Vec<&LeaderboardEntry> {
+        let mut entries: Vec<&LeaderboardEntry> = self.entries
+            .iter()
+            .filter(|e| mode.map_or(true, |m| e.mode == m) && seed.map_or(true, |s| e.seed == s))
+            .collect();
+        // The sort is stable, and entries are kept in the order of their ids
+        entries.sort_by(|a, b| b.score.cmp(&a.score));
+        entries
+    }
+
+    /// The replay of the entry with the given id, in the text format of `Replay`
+    pub fn replay_text(&self, id: u64) -> Option<String> {
+        let mut content = String::new();
+        fs::File::open(self.replay_path(id))
+            .and_then(|mut f| f.read_to_string(&mut content))
+            .ok()
+            .map(|_| content)
+    }
+
+    fn replay_path(&self, id: u64) -> PathBuf {
+        self.dir.join(REPLAYS_DIR_NAME).join(format!("{}.replay", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use types::GameMode;
+    use engine::Engine;
+
+    /// A finished game with scripted inputs
+    fn finished_game(seed: u64) -> Replay {
+        let mut engine = Engine::from_seed([800.0, 600.0], seed);
+        for step in 0.. {
+            let t = step as f64 / 60.0;
+            engine.set_hunter_pos([400.0 + 300.0 * t.cos(), 300.0 + 200.0 * (t * 1.3).sin()]);
+            if engine.update(1.0 / 60.0).is_err() {
+                break;
+            }
+        }
+        engine.replay().clone()
+    }
+
+    #[test]
+    fn submit_and_query() {
+        let dir = env::temp_dir()
+            .join(format!("catchit-leaderboard-submit-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut board = Leaderboard::open(&dir).unwrap();
+
+        let (first, second) = (finished_game(1), finished_game(2));
+        let mut tampered = first.clone();
+        tampered.outcome.as_mut().unwrap().score += 1;
+
+        let submission = Submission::verify("alice", &first.to_text()).unwrap();
+        let stored = board.submit(submission).unwrap().clone();
+        assert_eq!((stored.id, stored.score), (1, first.outcome.unwrap().score));
+        assert!(Submission::verify("mallory", &tampered.to_text()).unwrap_err().contains("score"));
+        assert!(Submission::verify("no spaces", &second.to_text()).is_err());
+        let submission = Submission::verify("bob", &second.to_text()).unwrap();
+        board.submit(submission.clone()).unwrap();
+        assert!(board.submit(submission).unwrap_err().contains("before"));
+
+        // Replays the engine can't play back are rejected before simulating them
+        let text = second.to_text();
+        for malformed in &[text.replace("field 800.0 600.0", "field 1 2"),
+                           text.replacen("u 0.016666666666666666", "u 1e12", 1),
+                           "catchit-replay 2\nseed 1".to_string()] {
+            assert!(Submission::verify("mallory", malformed).is_err());
+        }
+
+        let listed = board.query(Some(GameMode::Classic), Some(2));
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name, "bob");
+        assert!(board.query(Some(GameMode::Zen), None).is_empty());
+
+        // Everything survives a restart, including the replays
+        let mut board = Leaderboard::open(&dir).unwrap();
+        assert_eq!(board.query(None, None).len(), 2);
+        assert_eq!(board.replay_text(1), Some(first.to_text()));
+        let submission = Submission::verify("carol", &first.to_text()).unwrap();
+        assert!(board.submit(submission).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use catchit::Replay;
+
+/// How long to wait for the leaderboard, which plays the whole game again
+const TIMEOUT_SECS: u64 = 60;
+
+/// Escapes everything but unreserved characters, to be usable in a query
+fn percent_encode(value: &str) -> String {
+    let mut out = String::new();
+    for b in value.bytes() {
+        match b as char {
+            c if c.is_ascii_alphanumeric() || "-_.~".contains(c) => out.push(c),
+            _ => write!(out, "%{:02X}", b).unwrap(),
+        }
+    }
+    out
+}
+
+/// Sends a request to the leaderboard at `addr`, like `127.0.0.1:7878`, and
+/// returns the body of a successful response, or the reason it failed
+fn request(addr: &str, method: &str, target: &str, body: &str) -> Result<String, String> {
+    let exchange = || -> io::Result<String> {
+        let mut stream = try!(TcpStream::connect(addr));
+        try!(stream.set_read_timeout(Some(Duration::from_secs(TIMEOUT_SECS))));
+        try!(write!(stream,
+                    "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    method,
+                    target,
+                    addr,
+                    body.len(),
+                    body));
+        let mut response = String::new();
+        try!(stream.read_to_string(&mut response));
+        Ok(response)
+    };
+    let response = try!(exchange().map_err(|e| format!("Could not reach '{}': {}", addr, e)));
+    let status = response.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok());
+    let body = response.find("\r\n\r\n").map_or("", |pos| &response[pos + 4..]);
+    match status {
+        Some(status) if status / 100 == 2 => Ok(body.to_string()),
+        Some(_) => Err(body.trim().to_string()),
+        None => Err(format!("'{}' didn't answer like a leaderboard", addr)),
+    }
+}
+
+/// Submits the finished game recorded by `replay` to the leaderboard at `addr`.
+/// Returns the stored entry as `rank score name mode seed id`.
+pub fn submit_score(addr: &str, name: &str, replay: &Replay) -> Result<String, String> {
+    request(addr,
+            "POST",
+            &format!("/scores?name={}", percent_encode(name)),
+            &replay.to_text())
+}
@@ -0,0 +1,413 @@
+//! Levels made of waves, written in a small subset of TOML.
+//!
+//! ```toml
+//! name = "First Steps"
+//!
+//! [[wave]]
+//! name = "Warm-up"
+//! catch = 5            # or `survive = 30`, in seconds
+//!
+//! [[wave.obstacle]]
+//! kind = "deadly"
+//! pos = [0.25, 0.5]
+//! velocity = [0.1, -0.2]
+//!
+//! [[wave.spawn]]
+//! kind = "deadly"      # chosen like in the classic game if unset
+//! every = 4.0          # or `on_prey = true`
+//! start = 2.0
+//! count = 3
+//! lifetime = 10.0      # in seconds, forever if unset
+//! ```
+//!
+//! Positions and velocities are relative to the size of the playing field, with
+//! `[0, 0]` being its top-left and `[1, 1]` its bottom-right corner, so levels
+//! work with fields of any size.
+
+use types::{ObstacleKind, Position, Velocity};
+
+const BUILTIN_LEVELS: &'static [&'static str] = &[include_str!("../res/levels/01-first-steps.toml"),
+                                                  include_str!("../res/levels/02-crossfire.toml"),
+                                                  include_str!("../res/levels/03-swarm.toml")];
+/// The shortest time between obstacles of a spawn, in seconds
+const MIN_SPAWN_INTERVAL: f64 = 0.1;
+
+/// What it takes to finish a wave
+#[derive(Debug, Clone, PartialEq)]
+pub enum WinCondition {
+    /// Catch the given amount of prey
+    CatchPrey(u32),
+    /// Stay alive for the given amount of seconds
+    Survive(f64),
+}
+
+/// An obstacle which is present when its wave starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObstacleSpec {
+    pub kind: ObstacleKind,
+    /// Relative to the size of the field
+    pub pos: Position,
+    /// Relative to the size of the field, per second
+    pub velocity: Velocity,
+}
+
+/// When new obstacles appear during a wave
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpawnTrigger {
+    /// Every `every` seconds, the first one `start` seconds into the wave
+    Interval { start: f64, every: f64 },
+    /// Whenever the hunter catches prey
+    PreyCaught,
+}
+
+/// Obstacles appearing at random positions while a wave is played
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spawn {
+    /// Chosen at random like in the classic game if unset
+    pub kind: Option<ObstacleKind>,
+    pub trigger: SpawnTrigger,
+    /// The most obstacles to spawn, unlimited if unset
+    pub count: Option<u32>,
+    /// Seconds the obstacles stay, forever if unset
+    pub lifetime: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wave {
+    pub name: String,
+    pub win: WinCondition,
+    pub obstacles: Vec<ObstacleSpec>,
+    pub spawns: Vec<Spawn>,
+}
+
+/// Waves played one after another, which are all cleared from obstacles when
+/// the next one starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub waves: Vec<Wave>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Bool(bool),
+    Text(String),
+    Pair([f64; 2]),
+}
+
+/// A `[[table]]` with its `key = value` pairs, and the line numbers of both
+struct Table {
+    name: String,
+    line: usize,
+    pairs: Vec<(usize, String, Value)>,
+}
+
+/// Removes a `#` comment, unless it is part of a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (pos, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..pos],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(s: &str) -> Option<Value> {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        let text = &s[1..s.len() - 1];
+        return if text.contains('"') || text.contains('\\') {
+            None
+        } else {
+            Some(Value::Text(text.to_string()))
+        };
+    }
+    if s.starts_with('[') && s.ends_with(']') {
+        let numbers: Vec<f64> =
+            match s[1..s.len() - 1].split(',').map(|n| n.trim().parse().ok()).collect() {
+                Some(numbers) => numbers,
+                None => return None,
+            };
+        return if numbers.len() == 2 {
+            Some(Value::Pair([numbers[0], numbers[1]]))
+        } else {
+            None
+        };
+    }
+    match s {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => s.parse().ok().map(Value::Number),
+    }
+}
+
+fn parse_tables(content: &str) -> Result<Vec<Table>, String> {
+    let mut tables = vec![Table {
+                              name: String::new(),
+                              line: 1,
+                              pairs: Vec::new(),
+                          }];
+    for (lid, line) in content.lines().enumerate() {
+        let lid = lid + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with("[[") && line.ends_with("]]") {
+            tables.push(Table {
+                name: line[2..line.len() - 2].trim().to_string(),
+                line: lid,
+                pairs: Vec::new(),
+            });
+            continue;
+        }
+        let pos = try!(line.find('=').ok_or_else(|| format!("Line {}: expected 'key = value'", lid)));
+        let value = try!(parse_value(line[pos + 1..].trim())
+            .ok_or_else(|| format!("Line {}: invalid value", lid)));
+        tables.last_mut().unwrap().pairs.push((lid, line[..pos].trim().to_string(), value));
+    }
+    Ok(tables)
+}
+
+fn invalid(lid: usize, key: &str) -> String {
+    format!("Line {}: invalid '{}'", lid, key)
+}
+
+fn count(lid: usize, key: &str, value: &Value) -> Result<u32, String> {
+    match *value {
+        Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as u32),
+        _ => Err(invalid(lid, key)),
+    }
+}
+
+fn seconds(lid: usize, key: &str, value: &Value) -> Result<f64, String> {
+    match *value {
+        Value::Number(n) if n >= 0.0 && n.is_finite() => Ok(n),
+        _ => Err(invalid(lid, key)),
+    }
+}
+
+fn pair(lid: usize, key: &str, value: &Value) -> Result<[f64; 2], String> {
+    match *value {
+        Value::Pair(p) if p[0].is_finite() && p[1].is_finite() => Ok(p),
+        _ => Err(invalid(lid, key)),
+    }
+}
+
+fn text<'a>(lid: usize, key: &str, value: &'a Value) -> Result<&'a str, String> {
+    match *value {
+        Value::Text(ref t) => Ok(t),
+        _ => Err(invalid(lid, key)),
+    }
+}
+
+fn kind(lid: usize, key: &str, value: &Value) -> Result<ObstacleKind, String> {
+    text(lid, key, value).and_then(|t| ObstacleKind::from_name(t).ok_or_else(|| invalid(lid, key)))
+}
+
+fn parse_wave(table: &Table, index: usize) -> Result<Wave, String> {
+    let mut name = format!("Wave {}", index + 1);
+    let mut win = None;
+    for &(lid, ref key, ref value) in &table.pairs {
+        match &key[..] {
+            "name" => name = try!(text(lid, key, value)).to_string(),
+            "catch" if win.is_none() => {
+                win = Some(WinCondition::CatchPrey(try!(count(lid, key, value))))
+            }
+            "survive" if win.is_none() => {
+                win = Some(WinCondition::Survive(try!(seconds(lid, key, value))))
+            }
+            _ => return Err(invalid(lid, key)),
+        }
+    }
+    Ok(Wave {
+        name: name,
+        win: try!(win.ok_or_else(|| {
+            format!("Line {}: a wave needs either 'catch' or 'survive'", table.line)
+        })),
+        obstacles: Vec::new(),
+        spawns: Vec::new(),
+    })
+}
+
+fn parse_obstacle(table: &Table) -> Result<ObstacleSpec, String> {
+    let (mut obstacle_kind, mut pos, mut velocity) = (None, None, [0.0, 0.0]);
+    for &(lid, ref key, ref value) in &table.pairs {
+        match &key[..] {
+            "kind" => obstacle_kind = Some(try!(kind(lid, key, value))),
+            "pos" => pos = Some(try!(pair(lid, key, value))),
+            "velocity" => velocity = try!(pair(lid, key, value)),
+            _ => return Err(invalid(lid, key)),
+        }
+    }
+    match (obstacle_kind, pos) {
+        (Some(kind), Some(pos)) => {
+            Ok(ObstacleSpec {
+                kind: kind,
+                pos: pos,
+                velocity: velocity,
+            })
+        }
+        _ => Err(format!("Line {}: an obstacle needs 'kind' and 'pos'", table.line)),
+    }
+}
+
+fn parse_spawn(table: &Table) -> Result<Spawn, String> {
+    let (mut spawn_kind, mut every, mut start, mut on_prey, mut max) = (None, None, 0.0, false, None);
+    let mut lifetime = None;
+    for &(lid, ref key, ref value) in &table.pairs {
+        match (&key[..], value) {
+            ("kind", _) => spawn_kind = Some(try!(kind(lid, key, value))),
+            ("every", &Value::Number(n)) if n >= MIN_SPAWN_INTERVAL => every = Some(n),
+            ("every", _) => {
+                return Err(format!("Line {}: 'every' must be at least {} seconds",
+                                   lid,
+                                   MIN_SPAWN_INTERVAL))
+            }
+            ("start", _) => start = try!(seconds(lid, key, value)),
+            ("on_prey", &Value::Bool(b)) => on_prey = b,
+            ("count", _) => max = Some(try!(count(lid, key, value))),
+            ("lifetime", &Value::Number(n)) if n > 0.0 && n.is_finite() => lifetime = Some(n),
+            _ => return Err(invalid(lid, key)),
+        }
+    }
+    let trigger = match (every, on_prey) {
+        (Some(every), false) => {
+            SpawnTrigger::Interval {
+                start: start,
+                every: every,
+            }
+        }
+        (None, true) => SpawnTrigger::PreyCaught,
+        _ => {
+            return Err(format!("Line {}: a spawn needs either 'every' or 'on_prey = true'",
+                               table.line))
+        }
+    };
+    Ok(Spawn {
+        kind: spawn_kind,
+        trigger: trigger,
+        count: max,
+        lifetime: lifetime,
+    })
+}
+
+impl Level {
+    /// Parses a level, see the module documentation for the format
+    pub fn parse(content: &str) -> Result<Level, String> {
+        let tables = try!(parse_tables(content));
+        let mut level = Level {
+            name: String::new(),
+            waves: Vec::new(),
+        };
+        for table in &tables {
+            if table.name.is_empty() {
+                for &(lid, ref key, ref value) in &table.pairs {
+                    match &key[..] {
+                        "name" => level.name = try!(text(lid, key, value)).to_string(),
+                        _ => return Err(invalid(lid, key)),
+                    }
+                }
+                continue;
+            }
+
+            let index = level.waves.len();
+            if table.name == "wave" {
+                level.waves.push(try!(parse_wave(table, index)));
+                continue;
+            }
+            let wave = try!(level.waves
+                .last_mut()
+                .ok_or_else(|| format!("Line {}: expected a [[wave]] first", table.line)));
+            match &table.name[..] {
+                "wave.obstacle" => wave.obstacles.push(try!(parse_obstacle(table))),
+                "wave.spawn" => wave.spawns.push(try!(parse_spawn(table))),
+                _ => return Err(format!("Line {}: unknown table '{}'", table.line, table.name)),
+            }
+        }
+        if level.waves.is_empty() {
+            return Err("A level needs at least one [[wave]]".to_string());
+        }
+        Ok(level)
+    }
+
+    /// The levels of the built-in campaign, in the order they are played
+    pub fn builtin() -> Vec<Level> {
+        BUILTIN_LEVELS.iter()
+            .map(|content| Level::parse(content).expect("built-in levels to be valid"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::ObstacleKind;
+
+    #[test]
+    fn parse() {
+        let level = Level::parse(r#"
+            name = "Test" # comment
+            [[wave]]
+            catch = 3
+            [[wave.obstacle]]
+            kind = "deadly"
+            pos = [0.5, 0.25]
+            [[wave.spawn]]
+            every = 2.5
+            count = 4
+            [[wave]]
+            name = "Second # not a comment"
+            survive = 10
+            [[wave.spawn]]
+            kind = "invisibility_switch"
+            on_prey = true
+        "#)
+            .unwrap();
+        assert_eq!(level.name, "Test");
+        assert_eq!(level.waves.len(), 2);
+        let (first, second) = (&level.waves[0], &level.waves[1]);
+        assert_eq!(first.name, "Wave 1");
+        assert_eq!(first.win, WinCondition::CatchPrey(3));
+        assert_eq!(first.obstacles,
+                   vec![ObstacleSpec {
+                            kind: ObstacleKind::Deadly,
+                            pos: [0.5, 0.25],
+                            velocity: [0.0, 0.0],
+                        }]);
+        assert_eq!(first.spawns,
+                   vec![Spawn {
+                            kind: None,
+                            trigger: SpawnTrigger::Interval {
+                                start: 0.0,
+                                every: 2.5,
+                            },
+                            count: Some(4),
+                            lifetime: None,
+                        }]);
+        assert_eq!(second.name, "Second # not a comment");
+        assert_eq!(second.win, WinCondition::Survive(10.0));
+        assert_eq!(second.spawns[0].trigger, SpawnTrigger::PreyCaught);
+
+        for invalid in &["name = \"No waves\"",
+                         "[[wave.obstacle]]\nkind = \"deadly\"\npos = [0, 0]",
+                         "[[wave]]",
+                         "[[wave]]\ncatch = 1\nsurvive = 2",
+                         "[[wave]]\ncatch = 1.5",
+                         "[[wave]]\ncatch = 1\n[[wave.obstacle]]\nkind = \"rock\"\npos = [0, 0]",
+                         "[[wave]]\ncatch = 1\n[[wave.spawn]]\nevery = 1\non_prey = true",
+                         "[[wave]]\ncatch = 1\n[[boss]]"] {
+            assert!(Level::parse(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(Level::parse("[[wave]]\nsurvive = inf"),
+                   Err("Line 2: invalid 'survive'".to_string()));
+        assert_eq!(Level::parse("[[wave]]\ncatch = 1\n[[wave.obstacle]]\npos = [NaN, 0.5]"),
+                   Err("Line 4: invalid 'pos'".to_string()));
+        assert_eq!(Level::parse("[[wave]]\ncatch = 1\n[[wave.spawn]]\nevery = 0.01"),
+                   Err("Line 4: 'every' must be at least 0.1 seconds".to_string()));
+        assert_eq!(Level::builtin().len(), BUILTIN_LEVELS.len());
+    }
+}
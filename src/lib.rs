@@ -3,11 +3,40 @@
 extern crate vecmath;
 extern crate rand;
 
+mod achievements;
+mod animation;
+mod daily;
+mod deflate;
 mod engine;
+mod format;
+mod ghost;
+mod image;
+mod leaderboard;
+mod level;
+mod render;
+mod replay;
+mod software;
+mod spawn;
+mod theme;
 mod transition;
 mod types;
 
 pub use types::{Object, CollisionShape, ObstacleKind, State, Extent, Scalar, Pt, Position,
-                Velocity, Hunter, Obstacle};
+                Velocity, Hunter, Obstacle, Event, GameMode, WaveStatus, ObstacleLimits,
+                RecycleStrategy, Physics, Movement, Spring, ScoreSource, ScoreEntry};
 pub use engine::Engine;
+pub use achievements::{Achievement, Achievements, Goal};
+pub use animation::{Animation, AnimationFormat};
+pub use daily::Date;
+pub use format::parse_pairs;
+pub use ghost::Ghost;
+pub use image::Image;
+pub use leaderboard::{Leaderboard, LeaderboardEntry, Submission};
+pub use level::{Level, Wave, WinCondition, ObstacleSpec, Spawn, SpawnTrigger};
+pub use replay::{Replay, Input, Outcome, VerifiedScore, VerifyError, verify_replay};
+pub use render::{Renderer, Hud, FrameStats, HUD_SPACE};
+pub use software::SoftwareRenderer;
+pub use spawn::{SpawnPolicy, RandomPolicy, EdgeEntryPolicy, AwayFromHunterPolicy,
+                PoissonDiskPolicy};
+pub use theme::{Theme, Color, parse_color, format_color};
 pub use transition::{Transition, TransitionState, TransitionDirection};
@@ -2,10 +2,27 @@ extern crate piston;
 extern crate graphics;
 extern crate opengl_graphics;
 extern crate piston_window;
+extern crate rand;
+extern crate rodio;
 
 extern crate catchit;
 
-use catchit::{Engine, Object, CollisionShape, ObstacleKind, State, Extent};
+mod audio;
+mod config;
+mod gl_renderer;
+mod input;
+mod leaderboard_client;
+mod particles;
+mod prefs;
+mod scene;
+mod scenes;
+mod scores;
+mod screen;
+mod sound;
+
+use std::time::Instant;
+
+use catchit::{Extent, Renderer, FrameStats};
 use catchit::Scalar as CatchitScalar;
 
 use piston_window::*;
@@ -13,176 +30,166 @@ use opengl_graphics::glyph_cache::GlyphCache;
 use opengl_graphics::GlGraphics;
 use graphics::character::CharacterCache;
 use graphics::math::Scalar;
+use graphics::Transformed;
+
+use gl_renderer::GlRenderer;
+use input::Action;
+use scene::{Scene, SceneChange, Shared};
+use screen::Screen;
 
 pub struct App {
     gl: GlGraphics,
-    engine: Engine,
-    last_state: Option<State>,
-    field_border_y: Scalar,
-    max_score: u32,
-    text_height: f64,
-    tries: u32,
-    font_fira_bold: GlyphCache<'static>,
+    font: GlyphCache<'static>,
+    screen: Screen,
+    /// Height of capital letters in logical points
+    text_height: Scalar,
+    shared: Shared,
+    /// The top-most scene is the active one. If there is no scene, we are done.
+    scenes: Vec<Box<Scene>>,
+    /// Set while the debug overlay is shown
+    debug: Option<FrameStats>,
+    /// Frames drawn since `frames_since`, to compute the frame rate
+    frames: u32,
+    frames_since: Instant,
 }
 
 const WIDTH: u16 = 800;
 const HEIGHT: u16 = 600;
 const UPDATES_PER_SECOND: u64 = 60;
 const FONT_SIZE: u32 = 20;
-const HUD_SPACE: Scalar = 1.0 / 8.0;
-const NEW_GAME_TEXT: &'static str = "Press SPACE for new game";
 
 impl App {
     fn render(&mut self, args: &RenderArgs) {
-        use graphics::{rectangle, ellipse, clear, Transformed, Text, Line};
-        use graphics::math::Matrix2d;
-        use graphics::types::Color;
-
-        const BG: Color = [1.0, 204.0 / 255.0, 0.0, 1.0];
-        const BLACK: Color = [0.2, 0.2, 0.2, 1.0];
-        const WHITE: Color = [0.8, 0.8, 0.8, 1.0];
-        const BLUE: Color = [0.0, 0.0, 0.8, 1.0];
-        const RED: Color = [204.0 / 255.0, 0.0, 0.0, 1.0];
-
-        let square = rectangle::square(0.0, 0.0, 1.0);
-        let s = self.engine.state();
-        let game_over = self.game_over();
-        let font_fira_bold = &mut self.font_fira_bold;
+        let shared = &mut self.shared;
+        let scenes = &mut self.scenes;
+        let font = &mut self.font;
+        let screen = &mut self.screen;
         let text_height = self.text_height;
-        let max_score = self.max_score;
-        let tries = self.tries;
-        let field_border_y = self.field_border_y;
-        let last_state = &self.last_state;
-
-        self.gl.draw(args.viewport(), |c, gl| {
-            let draw_object = |obj: &Object, gl: &mut GlGraphics, color: Color| {
-                let transform = c.transform
-                    .trans(obj.pos[0] - obj.half_size, obj.pos[1] - obj.half_size)
-                    .scale(obj.half_size * 2.0, obj.half_size * 2.0);
-                match obj.shape {
-                    CollisionShape::Square => rectangle(color, square, transform, gl),
-                    CollisionShape::Circle => ellipse(color, square, transform, gl),
-                }
-            };
-
-            fn blend_color(c1: Color, c2: Color, blend: CatchitScalar) -> Color {
-                let mut c = c1;
-                for i in 0..3 {
-                    c[i] = blend as f32 * c1[i] + (1.0 - blend as f32) * c2[i];
-                }
-                c
+        let debug = &mut self.debug;
+        screen.update(args);
+
+        self.frames += 1;
+        let elapsed = seconds(self.frames_since);
+        if elapsed >= 1.0 {
+            if let Some(ref mut stats) = *debug {
+                stats.fps = self.frames as f64 / elapsed;
             }
+            self.frames = 0;
+            self.frames_since = Instant::now();
+        }
 
-            clear(BG, gl);
-
-            let text = Text::new_color(BLACK, FONT_SIZE);
-            let text_matrix = |x: Scalar| -> Matrix2d {
-                c.transform.trans(x, HEIGHT as Scalar - text_height / 2.0)
+        self.gl.draw(args.viewport(), |c, gl| {
+            let mut r = GlRenderer {
+                // From here on, we draw in logical points
+                c: c.trans(screen.offset[0], screen.offset[1]).zoom(screen.zoom),
+                gl: gl,
+                font: font,
+                screen: screen,
+                text_height: text_height,
             };
-
-            if let Some(ref s) = s.as_ref().or(last_state.as_ref()) {
-                let deadly_color = blend_color(BLACK, BG, s.obstacle_opacity.current);
-                let hunter_color = blend_color(RED,
-                                               BLUE,
-                                               1.0 -
-                                               s.attracting_force.current / s.attracting_force.v2);
-
-                for obstacle in &s.obstacles {
-                    let color = match obstacle.kind {
-                        ObstacleKind::Deadly => deadly_color,
-                        ObstacleKind::AttractiveForceSwitch => BLUE,
-                        ObstacleKind::InvisibiltySwitch => WHITE,
-                    };
-                    draw_object(&obstacle.object, gl, color);
-                }
-
-                draw_object(&s.prey, gl, RED);
-                draw_object(&s.hunter.object, gl, hunter_color);
-
-                text.draw(&format!("Score: {}", s.score),
-                          font_fira_bold,
-                          &c.draw_state,
-                          text_matrix(WIDTH as Scalar * HUD_SPACE * 4.5),
-                          gl);
-
-                text.draw(&format!("Multiplier: {:.2}", s.score_coeff),
-                          font_fira_bold,
-                          &c.draw_state,
-                          text_matrix(WIDTH as Scalar * HUD_SPACE * 6.0),
-                          gl);
+            r.clear(shared.theme().background);
+            if let Some(scene) = scenes.last_mut() {
+                scene.render(shared, &mut r);
             }
-
-            if game_over {
-                let w = text_width(font_fira_bold, NEW_GAME_TEXT) / 2.0;
-                text.draw(NEW_GAME_TEXT,
-                          font_fira_bold,
-                          &c.draw_state,
-                          c.transform.trans(WIDTH as Scalar / 2.0 - w, HEIGHT as Scalar / 2.0),
-                          gl);
-                text.draw("Use LMB for repelling force",
-                          font_fira_bold,
-                          &c.draw_state,
-                          c.transform.trans(WIDTH as Scalar / 2.0 - w,
-                                            HEIGHT as Scalar / 2.0 + text_height +
-                                            text_height * 0.4),
-                          gl);
+            if let Some(ref stats) = *debug {
+                r.draw_debug_overlay(shared.engine.state().as_ref(), stats);
             }
+            shared.draw_toasts(&mut r);
+        });
+    }
 
-            // Draw HUD
-            // /////////
-            let line = Line::new(BLACK, 1.0);
-
-            line.draw([0.0, 0.0, WIDTH as Scalar, 0.0],
-                      &c.draw_state,
-                      c.transform.trans(0.0, field_border_y),
-                      gl);
-
-            text.draw(&format!("Best Score: {}", max_score),
-                      font_fira_bold,
-                      &c.draw_state,
-                      text_matrix(WIDTH as Scalar * HUD_SPACE * 1.0),
-                      gl);
+    fn update(&mut self, args: &UpdateArgs) {
+        self.shared.audio.update_music(self.shared.engine.state().as_ref());
+        self.shared.update_toasts(args.dt);
+        let start = Instant::now();
+        let change = match self.scenes.last_mut() {
+            Some(scene) => scene.update(&mut self.shared, args.dt),
+            None => return,
+        };
+        if let Some(ref mut stats) = self.debug {
+            stats.update_time = seconds(start);
+        }
+        self.apply(change);
+    }
 
-            text.draw(&format!("Tries: {}", tries),
-                      font_fira_bold,
-                      &c.draw_state,
-                      text_matrix(WIDTH as Scalar * HUD_SPACE * 3.0),
-                      gl);
+    fn on_press(&mut self, button: Button) {
+        if self.shared.bindings.action(&button) == Some(Action::Screenshot) {
+            if let Some(scene) = self.scenes.last_mut() {
+                scene::save_screenshot(&mut **scene,
+                                       &mut self.shared,
+                                       self.text_height,
+                                       self.screen.pixels_per_point());
+            }
+            return;
+        }
+        if self.shared.bindings.action(&button) == Some(Action::DebugOverlay) {
+            self.debug = match self.debug {
+                Some(_) => None,
+                None => Some(FrameStats::default()),
+            };
+            return;
+        }
+        let change = match self.scenes.last_mut() {
+            Some(scene) => scene.on_press(&mut self.shared, button),
+            None => return,
+        };
+        self.apply(change);
+    }
 
-        });
+    fn on_release(&mut self, button: Button) {
+        let change = match self.scenes.last_mut() {
+            Some(scene) => scene.on_release(&mut self.shared, button),
+            None => return,
+        };
+        self.apply(change);
     }
 
-    fn game_over(&self) -> bool {
-        self.last_state.is_some()
+    /// `pos` is in window points
+    fn on_mouse(&mut self, pos: [Scalar; 2]) {
+        let pos = self.screen.to_logical(pos);
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.on_mouse(&mut self.shared, pos);
+        }
     }
 
-    fn update(&mut self, args: &UpdateArgs) {
-        if let Err(state) = self.engine.update(args.dt) {
-            if state.score > self.max_score {
-                self.max_score += state.score;
+    fn apply(&mut self, change: SceneChange) {
+        match change {
+            SceneChange::Stay => {}
+            SceneChange::Push(scene) => self.scenes.push(scene),
+            SceneChange::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+            SceneChange::Pop => {
+                self.scenes.pop();
+            }
+            SceneChange::Reset(scene) => {
+                self.scenes.clear();
+                self.scenes.push(scene);
             }
-            self.tries += 1;
-            self.last_state = Some(state);
+            SceneChange::Quit => self.scenes.clear(),
         }
     }
+
+    fn is_done(&self) -> bool {
+        self.scenes.is_empty()
+    }
 }
 
-fn compute_field(width: u16, height: u16, text_height: Scalar) -> Extent {
-    [width as CatchitScalar, height as CatchitScalar - (text_height * 2.0)]
+/// Seconds passed since the given instant
+fn seconds(since: Instant) -> f64 {
+    let elapsed = since.elapsed();
+    elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9
 }
 
-fn text_width(cache: &mut GlyphCache<'static>, text: &str) -> Scalar {
-    let mut w = 0.0;
-    for c in text.chars() {
-        w += cache.character(FONT_SIZE, c).width();
-    }
-    w
+fn compute_field(width: u16, height: u16, text_height: Scalar) -> Extent {
+    [width as CatchitScalar, height as CatchitScalar - (text_height * 2.0)]
 }
 
 fn main() {
     // Create an Glutin window.
     let mut window: PistonWindow = WindowSettings::new("catchit", (WIDTH as u32, HEIGHT as u32))
-        .exit_on_esc(true)
+        .exit_on_esc(false)
         .vsync(true)
         .build()
         .unwrap();
@@ -194,16 +201,16 @@ fn main() {
         let text_height = glyphs.character(FONT_SIZE, 'S').top();
         let field = compute_field(WIDTH, HEIGHT, text_height);
 
-        // Create a new game and run it.
         App {
             gl: gl,
-            engine: Engine::from_field(field),
-            last_state: None,
-            field_border_y: field[1],
+            font: glyphs,
+            screen: Screen::new([WIDTH as Scalar, HEIGHT as Scalar]),
             text_height: text_height,
-            tries: 0,
-            max_score: 0,
-            font_fira_bold: glyphs,
+            shared: Shared::new(field),
+            scenes: vec![Box::new(scenes::Title::default())],
+            debug: None,
+            frames: 0,
+            frames_since: Instant::now(),
         }
     };
 
@@ -214,22 +221,15 @@ fn main() {
 
     while let Some(e) = events.next(&mut window) {
         if let Some(pos) = e.mouse_cursor_args() {
-            app.engine.set_hunter_pos(pos);
+            app.on_mouse(pos);
         }
 
-        match e.press_args() {
-            Some(Button::Keyboard(Key::Space)) if app.game_over() => {
-                app.last_state = None;
-                app.engine.reset(compute_field(WIDTH, HEIGHT, app.text_height))
-            }
-            Some(Button::Mouse(MouseButton::Left)) => {
-                app.engine.set_hunter_force(true);
-            }
-            _ => {}
+        if let Some(button) = e.press_args() {
+            app.on_press(button);
         }
 
-        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
-            app.engine.set_hunter_force(false);
+        if let Some(button) = e.release_args() {
+            app.on_release(button);
         }
 
         if let Some(r) = e.render_args() {
@@ -239,5 +239,9 @@ fn main() {
         if let Some(u) = e.update_args() {
             app.update(&u);
         }
+
+        if app.is_done() {
+            window.set_should_close(true);
+        }
     }
 }
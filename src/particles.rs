@@ -0,0 +1,137 @@
+use std::f64::consts::PI;
+
+use rand::{self, Rng};
+
+use catchit::{Event, ObstacleKind, Position, Velocity, Theme, Renderer, Color, Scalar};
+
+const BURST_PARTICLES: usize = 24;
+const BURST_SPEED: Scalar = 150.0;
+const BURST_LIFETIME: f64 = 0.5;
+const RIPPLE_PARTICLES: usize = 32;
+const RIPPLE_SPEED: Scalar = 200.0;
+const RIPPLE_LIFETIME: f64 = 0.6;
+const EXPLOSION_PARTICLES: usize = 96;
+const EXPLOSION_SPEED: Scalar = 300.0;
+const EXPLOSION_LIFETIME: f64 = 1.5;
+const PARTICLE_SIZE: Scalar = 3.0;
+/// Fraction of velocity particles keep per second
+const PARTICLE_DAMPING: Scalar = 0.2;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Particle {
+    pos: Position,
+    velocity: Velocity,
+    age: f64,
+    lifetime: f64,
+    half_size: Scalar,
+    color: Color,
+}
+
+/// Purely visual particles, which are spawned in response to engine events
+pub struct Particles {
+    particles: Vec<Particle>,
+    rng: rand::XorShiftRng,
+}
+
+impl Particles {
+    pub fn new() -> Particles {
+        Particles {
+            particles: Vec::new(),
+            rng: rand::weak_rng(),
+        }
+    }
+
+    /// Spawn particles matching the given event, coloured like the objects involved
+    pub fn on_event(&mut self, event: &Event, theme: &Theme) {
+        match *event {
+            Event::PreyCaught { pos, .. } => self.burst(pos, theme.prey),
+            Event::SwitchHit { ref kind, pos } => {
+                self.ripple(pos,
+                            match *kind {
+                                ObstacleKind::InvisibiltySwitch => theme.invisibility_switch,
+                                _ => theme.attractive_switch,
+                            })
+            }
+            Event::GameOver { pos } => self.explosion(pos, theme.foreground),
+            Event::TimeUp |
+            Event::WaveCleared { .. } |
+            Event::CampaignComplete |
+            Event::ObstacleDespawned { .. } => {}
+        }
+    }
+
+    /// Particles flying into random directions at random speeds
+    pub fn burst(&mut self, pos: Position, color: Color) {
+        for _ in 0..BURST_PARTICLES {
+            let angle = self.rng.gen_range(0.0, 2.0 * PI);
+            let speed = self.rng.gen_range(0.2, 1.0) * BURST_SPEED;
+            self.spawn(pos, angle, speed, BURST_LIFETIME, PARTICLE_SIZE, color);
+        }
+    }
+
+    /// A ring of particles expanding evenly
+    pub fn ripple(&mut self, pos: Position, color: Color) {
+        for pid in 0..RIPPLE_PARTICLES {
+            let angle = pid as Scalar / RIPPLE_PARTICLES as Scalar * 2.0 * PI;
+            self.spawn(pos, angle, RIPPLE_SPEED, RIPPLE_LIFETIME, PARTICLE_SIZE * 0.5, color);
+        }
+    }
+
+    /// Like a burst, but bigger and longer lasting
+    pub fn explosion(&mut self, pos: Position, color: Color) {
+        for _ in 0..EXPLOSION_PARTICLES {
+            let angle = self.rng.gen_range(0.0, 2.0 * PI);
+            let speed = self.rng.gen_range(0.1, 1.0) * EXPLOSION_SPEED;
+            let lifetime = self.rng.gen_range(0.5, 1.0) * EXPLOSION_LIFETIME;
+            let half_size = self.rng.gen_range(1.0, 2.0) * PARTICLE_SIZE;
+            self.spawn(pos, angle, speed, lifetime, half_size, color);
+        }
+    }
+
+    fn spawn(&mut self,
+             pos: Position,
+             angle: Scalar,
+             speed: Scalar,
+             lifetime: f64,
+             half_size: Scalar,
+             color: Color) {
+        self.particles.push(Particle {
+            pos: pos,
+            velocity: [angle.cos() * speed, angle.sin() * speed],
+            age: 0.0,
+            lifetime: lifetime,
+            half_size: half_size,
+            color: color,
+        });
+    }
+
+    /// Move all particles and remove the ones which expired
+    pub fn update(&mut self, dt: f64) {
+        let damping = PARTICLE_DAMPING.powf(dt);
+        for p in &mut self.particles {
+            p.age += dt;
+            p.pos[0] += p.velocity[0] * dt;
+            p.pos[1] += p.velocity[1] * dt;
+            p.velocity[0] *= damping;
+            p.velocity[1] *= damping;
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Draw all particles, fading them out as they get older
+    pub fn draw(&self, r: &mut Renderer) {
+        for p in &self.particles {
+            let mut color = p.color;
+            color[3] *= (1.0 - p.age / p.lifetime) as f32;
+            r.ellipse([p.pos[0] - p.half_size,
+                       p.pos[1] - p.half_size,
+                       p.half_size * 2.0,
+                       p.half_size * 2.0],
+                      color);
+        }
+    }
+}
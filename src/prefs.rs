@@ -0,0 +1,102 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use catchit::{Theme, Movement, parse_pairs};
+
+use audio::Volume;
+use config;
+
+const DEFAULT_PLAYER: &'static str = "anonymous";
+
+/// User preferences which persist between runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preferences {
+    pub volume: Volume,
+    /// Name of the theme to use
+    pub theme: String,
+    /// How the hunter follows the mouse
+    pub movement: Movement,
+    /// Address of the leaderboard server finished games are submitted to, if any
+    pub leaderboard: Option<String>,
+    /// The name scores are submitted under
+    pub player: String,
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            volume: Volume::default(),
+            theme: Theme::classic().name,
+            movement: Movement::default(),
+            leaderboard: None,
+            player: DEFAULT_PLAYER.to_string(),
+        }
+    }
+}
+
+impl Preferences {
+    /// Load preferences from the given file. Unknown keys and unparseable values
+    /// are ignored, keeping their default.
+    pub fn load(path: &Path) -> Preferences {
+        let mut prefs = Preferences::default();
+        let content = match config::read_file(path) {
+            Ok(content) => content,
+            Err(_) => return prefs,
+        };
+        let pairs = match parse_pairs(&content) {
+            Ok(pairs) => pairs,
+            Err(line) => {
+                println!("Ignoring preferences at '{}': line {} is malformed",
+                         path.display(),
+                         line);
+                return prefs;
+            }
+        };
+
+        for (key, value) in pairs {
+            if key == "theme" {
+                prefs.theme = value;
+                continue;
+            }
+            if key == "movement" {
+                // Older versions only saved the name of the movement
+                prefs.movement = Movement::parse(&value)
+                    .or_else(|| Movement::from_name(&value))
+                    .unwrap_or(prefs.movement);
+                continue;
+            }
+            if key == "leaderboard" {
+                prefs.leaderboard = if value.is_empty() { None } else { Some(value) };
+                continue;
+            }
+            if key == "player" {
+                prefs.player = value;
+                continue;
+            }
+            let volume = match &key[..] {
+                "master_volume" => &mut prefs.volume.master,
+                "effects_volume" => &mut prefs.volume.effects,
+                "music_volume" => &mut prefs.volume.music,
+                _ => continue,
+            };
+            if let Ok(v) = value.parse::<f32>() {
+                *volume = v.max(0.0).min(1.0);
+            }
+        }
+        prefs
+    }
+
+    pub fn save(&self, path: &Path) -> ::std::io::Result<()> {
+        let mut out = String::new();
+        writeln!(out, "master_volume = {}", self.volume.master).unwrap();
+        writeln!(out, "effects_volume = {}", self.volume.effects).unwrap();
+        writeln!(out, "music_volume = {}", self.volume.music).unwrap();
+        writeln!(out, "theme = {}", self.theme).unwrap();
+        writeln!(out, "movement = {}", self.movement.to_text()).unwrap();
+        writeln!(out, "player = {}", self.player).unwrap();
+        if let Some(ref addr) = self.leaderboard {
+            writeln!(out, "leaderboard = {}", addr).unwrap();
+        }
+        config::write_file(path, out.as_bytes())
+    }
+}
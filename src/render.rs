@@ -0,0 +1,243 @@
+use std::f64::consts::PI;
+
+use vecmath::{vec2_add, vec2_scale};
+
+use types::{Object, CollisionShape, ObstacleKind, State, Extent, Scalar, Position, Hunter,
+            GameMode, WaveStatus, COMBO_WINDOW};
+use transition::Transition;
+use theme::{Theme, Color};
+use level::WinCondition;
+
+/// Horizontal space of one HUD column, relative to the width of the screen
+pub const HUD_SPACE: Scalar = 1.0 / 8.0;
+/// Opacity of the hunter of a recorded game
+const GHOST_ALPHA: f32 = 0.35;
+/// Height of the bar below the playing field which shows the time left to continue a combo
+const COMBO_BAR_HEIGHT: Scalar = 3.0;
+/// Colour of everything drawn by the debug overlay, chosen to stand out in any theme
+const DEBUG_COLOR: Color = [1.0, 0.0, 1.0, 1.0];
+/// Velocities are drawn as the distance covered in this many seconds
+const DEBUG_VELOCITY_SECONDS: Scalar = 0.25;
+/// Circles are outlined with this many lines
+const DEBUG_CIRCLE_SEGMENTS: usize = 24;
+
+/// Timings of the game loop, shown by the debug overlay
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FrameStats {
+    /// Frames drawn in the last second
+    pub fps: f64,
+    /// Seconds the last update took
+    pub update_time: f64,
+}
+
+/// Information displayed below the playing field, which is not part of the `State`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hud {
+    /// Size of the whole screen in logical points
+    pub size: Extent,
+    /// Size of the playing field, which is located at the top of the screen
+    pub field: Extent,
+    pub best_score: u32,
+    pub tries: u32,
+    /// Score of the recorded game the player races against, if any
+    pub ghost_score: Option<u32>,
+}
+
+fn blend_color(c1: Color, c2: Color, blend: Scalar) -> Color {
+    let mut c = c1;
+    for i in 0..3 {
+        c[i] = blend as f32 * c1[i] + (1.0 - blend as f32) * c2[i];
+    }
+    c
+}
+
+fn describe_transition(name: &str, t: &Transition) -> String {
+    format!("{}: {:.2} ({:?}, held {:.1}s)", name, t.current, t.direction, t.state_time)
+}
+
+/// Which wave is played, and how far it is from being won
+fn wave_progress(w: &WaveStatus) -> String {
+    let progress = match w.win {
+        WinCondition::CatchPrey(count) => format!("{}/{}", w.caught, count),
+        WinCondition::Survive(seconds) => format!("{}s", (seconds - w.time).max(0.0).ceil()),
+    };
+    format!("Wave {}-{}: {}", w.level + 1, w.wave + 1, progress)
+}
+
+/// Something which can draw simple shapes and text, in logical points.
+///
+/// Implementors only provide the primitives, and get the drawing of the game
+/// for free.
+pub trait Renderer {
+    fn clear(&mut self, color: Color);
+
+    /// Fill the rectangle `[x, y, width, height]`
+    fn rectangle(&mut self, rect: [Scalar; 4], color: Color);
+
+    /// Fill the ellipse within the rectangle `[x, y, width, height]`
+    fn ellipse(&mut self, rect: [Scalar; 4], color: Color);
+
+    fn line(&mut self, from: Position, to: Position, width: Scalar, color: Color);
+
+    /// Draw `text` with its baseline starting at `pos`
+    fn text(&mut self, text: &str, pos: Position, color: Color);
+
+    fn text_width(&mut self, text: &str) -> Scalar;
+
+    /// Height of capital letters
+    fn text_height(&self) -> Scalar;
+
+    fn draw_object(&mut self, obj: &Object, color: Color) {
+        let rect = [obj.left(), obj.top(), obj.half_size * 2.0, obj.half_size * 2.0];
+        match obj.shape {
+            CollisionShape::Square => self.rectangle(rect, color),
+            CollisionShape::Circle => self.ellipse(rect, color),
+        }
+    }
+
+    /// Draws all objects of the playing field
+    fn draw_state(&mut self, s: &State, theme: &Theme) {
+        let deadly_color = blend_color(theme.foreground,
+                                       theme.background,
+                                       s.obstacle_opacity.current);
+        let hunter_color = blend_color(theme.hunter,
+                                       theme.hunter_attracting,
+                                       1.0 - s.attracting_force.current / s.attracting_force.v2);
+
+        for obstacle in &s.obstacles {
+            let mut color = match obstacle.kind {
+                ObstacleKind::Deadly => deadly_color,
+                ObstacleKind::AttractiveForceSwitch => theme.attractive_switch,
+                ObstacleKind::InvisibiltySwitch => theme.invisibility_switch,
+            };
+            color[3] *= obstacle.opacity() as f32;
+            self.draw_object(&obstacle.object, color);
+        }
+
+        self.draw_object(&s.prey, theme.prey);
+        self.draw_object(&s.hunter.object, hunter_color);
+    }
+
+    /// Draws the hunter of a recorded game, translucent to tell it apart from the player's
+    fn draw_ghost(&mut self, hunter: &Hunter, theme: &Theme) {
+        let mut color = theme.hunter;
+        color[3] *= GHOST_ALPHA;
+        self.draw_object(&hunter.object, color);
+    }
+
+    /// Draws the border of the playing field and the information below it.
+    /// Information about the current game is only shown if `s` is set.
+    fn draw_hud(&mut self, s: Option<&State>, hud: &Hud, theme: &Theme) {
+        let text_y = hud.size[1] - self.text_height() / 2.0;
+        self.line([0.0, hud.field[1]], [hud.size[0], hud.field[1]], 2.0, theme.foreground);
+
+        // Waves and modes other than the classic one show their progress instead of the tries
+        let mode_entry = match (s.and_then(|s| s.wave.as_ref()), s.map(|s| (s.mode, s.time))) {
+            (Some(w), _) => wave_progress(w),
+            (None, Some((GameMode::TimeAttack, time))) => {
+                let limit = GameMode::TimeAttack.time_limit().unwrap_or(0.0);
+                format!("Time left: {}", (limit - time).max(0.0).ceil())
+            }
+            (None, Some((GameMode::Survival, time))) => format!("Alive: {}s", time.floor()),
+            (None, Some((GameMode::Zen, _))) => "Zen".to_string(),
+            _ => format!("Tries: {}", hud.tries),
+        };
+        let mut entries = vec![(1.0, format!("Best Score: {}", hud.best_score)), (3.0, mode_entry)];
+        if let Some(s) = s {
+            let score = match hud.ghost_score {
+                Some(ghost) => format!("Score: {} ({:+})", s.score, s.score as i64 - ghost as i64),
+                None => format!("Score: {}", s.score),
+            };
+            entries.push((4.5, score));
+            entries.push((6.0, format!("Multiplier: {:.2}", s.score_coeff)));
+            // A running combo is shown in the corner of the field, with a bar along its
+            // bottom shrinking as the time to continue it runs out
+            if s.combo > 1 {
+                let bar_top = hud.field[1] - 1.0 - COMBO_BAR_HEIGHT;
+                let width = hud.field[0] * s.combo_time_left / COMBO_WINDOW;
+                self.rectangle([0.0, bar_top, width, COMBO_BAR_HEIGHT], theme.prey);
+                let combo = format!("Combo x{}", s.combo);
+                let x = hud.field[0] - self.text_width(&combo) - self.text_height();
+                self.text(&combo, [x, bar_top - self.text_height() / 2.0], theme.prey);
+            }
+        }
+
+        for &(column, ref entry) in &entries {
+            self.text(entry, [hud.size[0] * HUD_SPACE * column, text_y], theme.foreground);
+        }
+    }
+
+    fn circle_outline(&mut self, center: Position, radius: Scalar, width: Scalar, color: Color) {
+        let point = |i: usize| {
+            let angle = 2.0 * PI * i as Scalar / DEBUG_CIRCLE_SEGMENTS as Scalar;
+            [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+        };
+        for i in 0..DEBUG_CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), width, color);
+        }
+    }
+
+    /// Outlines the area the engine checks for collisions with the object
+    fn draw_bounds(&mut self, obj: &Object, color: Color) {
+        match obj.shape {
+            CollisionShape::Circle => self.circle_outline(obj.pos, obj.half_size, 1.0, color),
+            CollisionShape::Square => {
+                let corners = [[obj.left(), obj.top()],
+                               [obj.right(), obj.top()],
+                               [obj.right(), obj.bottom()],
+                               [obj.left(), obj.bottom()]];
+                for i in 0..corners.len() {
+                    self.line(corners[i], corners[(i + 1) % corners.len()], 1.0, color);
+                }
+            }
+        }
+    }
+
+    /// Draws what the engine sees on top of the frame: the collision bounds and
+    /// velocities of all objects, the reach of the hunter's force, the transitions
+    /// and the timings of the game loop
+    fn draw_debug_overlay(&mut self, s: Option<&State>, stats: &FrameStats) {
+        let mut lines = vec![format!("FPS: {:.0}, update: {:.2}ms",
+                                     stats.fps,
+                                     stats.update_time * 1000.0)];
+        if let Some(s) = s {
+            let moving = s.obstacles
+                .iter()
+                .map(|o| (&o.object, o.velocity))
+                .chain(Some((&s.hunter.object, s.hunter.velocity)));
+            for (obj, velocity) in moving {
+                self.draw_bounds(obj, DEBUG_COLOR);
+                let ahead = vec2_add(obj.pos, vec2_scale(velocity, DEBUG_VELOCITY_SECONDS));
+                self.line(obj.pos, ahead, 1.0, DEBUG_COLOR);
+            }
+            self.draw_bounds(&s.prey, DEBUG_COLOR);
+            let force_width = if s.hunter.force != 0.0 { 2.0 } else { 1.0 };
+            self.circle_outline(s.hunter.object.pos,
+                                s.hunter.force_radius(),
+                                force_width,
+                                DEBUG_COLOR);
+
+            lines.push(format!("Time: {:.2}s, dt: {:.4}s, obstacles: {}",
+                               s.time,
+                               s.last_dt,
+                               s.obstacles.len()));
+            lines.push(describe_transition("Opacity", &s.obstacle_opacity));
+            lines.push(describe_transition("Attraction", &s.attracting_force));
+        }
+
+        let x = self.text_height();
+        for (lid, line) in lines.iter().enumerate() {
+            let y = self.text_height() * 2.0 * (lid + 1) as Scalar;
+            self.text(line, [x, y], DEBUG_COLOR);
+        }
+    }
+
+    /// Draws a complete frame, consisting of the playing field and the HUD
+    fn draw_scene(&mut self, s: Option<&State>, hud: &Hud, theme: &Theme) {
+        self.clear(theme.background);
+        if let Some(s) = s {
+            self.draw_state(s, theme);
+        }
+        self.draw_hud(s, hud, theme);
+    }
+}
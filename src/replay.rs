@@ -0,0 +1,432 @@
+use std::fmt::{self, Write};
+
+use types::{State, Extent, Scalar, Position, GameMode, Movement};
+use engine::{Engine, MIN_FIELD_SIZE};
+use daily::Date;
+
+const HEADER: &'static str = "catchit-replay 2";
+/// The header of replays recorded before obstacles were moved by forces, which
+/// can't be played back the same anymore
+const OUTDATED_HEADER: &'static str = "catchit-replay 1";
+/// The longest time a single update may advance the game, in seconds. The game
+/// itself updates far more often.
+const MAX_UPDATE_TIME: f64 = 0.25;
+
+/// Something the player did, in the order it was given to the engine
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    HunterPos(Position),
+    HunterForce(bool),
+    /// Time passed, in seconds
+    Update(f64),
+}
+
+/// How a game ended, as claimed by whoever recorded it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outcome {
+    pub score: u32,
+    /// The amount of updates until the game was over, including the last one
+    pub ticks: usize,
+}
+
+/// Everything needed to play a game again exactly as it happened: the seed of
+/// its random numbers, its rules, the hunter's movement, the playing field, and
+/// all inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    /// The day of the daily challenge this game was played in, if any
+    pub daily: Option<Date>,
+    pub mode: GameMode,
+    /// How the hunter followed the player
+    pub movement: Movement,
+    pub field: Extent,
+    /// How the game ended, if it did
+    pub outcome: Option<Outcome>,
+    pub inputs: Vec<Input>,
+}
+
+/// A score confirmed by playing its replay again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerifiedScore {
+    pub score: u32,
+    pub ticks: usize,
+    /// Time the game took, in seconds
+    pub time: f64,
+}
+
+/// Why the outcome claimed by a replay can't be trusted
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// The replay doesn't claim any outcome
+    Unclaimed,
+    /// The inputs don't lead to the end of the game
+    NotOver,
+    /// There are inputs after the game was over, which the game never records
+    InputsAfterGameOver,
+    ScoreMismatch { claimed: u32, actual: u32 },
+    TickMismatch { claimed: usize, actual: usize },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VerifyError::Unclaimed => write!(f, "The replay claims no outcome"),
+            VerifyError::NotOver => write!(f, "The game isn't over at the end of the replay"),
+            VerifyError::InputsAfterGameOver => {
+                write!(f, "The replay continues after the game was over")
+            }
+            VerifyError::ScoreMismatch { claimed, actual } => {
+                write!(f, "The claimed score is {}, but the game ended with {}", claimed, actual)
+            }
+            VerifyError::TickMismatch { claimed, actual } => {
+                write!(f,
+                       "The game is claimed to end after {} updates, but ended after {}",
+                       claimed,
+                       actual)
+            }
+        }
+    }
+}
+
+/// Plays the replay again, and checks that the game ends exactly as it claims
+pub fn verify_replay(replay: &Replay) -> Result<VerifiedScore, VerifyError> {
+    let claimed = try!(replay.outcome.ok_or(VerifyError::Unclaimed));
+    let mut engine = Engine::from_replay(replay);
+    let mut ticks = 0;
+    let mut time = 0.0;
+    let mut last = None;
+    let mut inputs = replay.inputs.iter();
+    for input in inputs.by_ref() {
+        if let Input::Update(dt) = *input {
+            ticks += 1;
+            time += dt;
+        }
+        if let Err(state) = engine.apply(input) {
+            last = Some(state);
+            break;
+        }
+    }
+    let last = try!(last.ok_or(VerifyError::NotOver));
+    if inputs.next().is_some() {
+        return Err(VerifyError::InputsAfterGameOver);
+    }
+    if last.score != claimed.score {
+        return Err(VerifyError::ScoreMismatch {
+            claimed: claimed.score,
+            actual: last.score,
+        });
+    }
+    if ticks != claimed.ticks {
+        return Err(VerifyError::TickMismatch {
+            claimed: claimed.ticks,
+            actual: ticks,
+        });
+    }
+    Ok(VerifiedScore {
+        score: last.score,
+        ticks: ticks,
+        time: time,
+    })
+}
+
+impl Replay {
+    pub fn new(seed: u64, field: Extent) -> Replay {
+        Replay {
+            seed: seed,
+            daily: None,
+            mode: GameMode::Classic,
+            movement: Movement::Direct,
+            field: field,
+            outcome: None,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// The amount of updates
+    pub fn ticks(&self) -> usize {
+        self.inputs
+            .iter()
+            .filter(|i| match **i {
+                Input::Update(_) => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    /// Time the game took, in seconds
+    pub fn duration(&self) -> f64 {
+        self.inputs
+            .iter()
+            .map(|i| match *i {
+                Input::Update(dt) => dt,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    /// Plays the game again, calling `on_update` with the total time passed and the
+    /// state after every update. Returns the final state if the game ended.
+    pub fn simulate<F>(&self, mut on_update: F) -> Option<State>
+        where F: FnMut(f64, &State)
+    {
+        let mut engine = Engine::from_replay(self);
+        let mut time = 0.0;
+        for input in &self.inputs {
+            let result = engine.apply(input);
+            if let Input::Update(dt) = *input {
+                time += dt;
+            }
+            match result {
+                Err(state) => {
+                    on_update(time, &state);
+                    return Some(state);
+                }
+                Ok(()) => {
+                    if let (&Input::Update(_), &Some(ref s)) = (input, engine.state()) {
+                        on_update(time, s);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Parses the line-based format written by `to_text()`. Replays of a daily
+    /// challenge must use the seed of their day, and nothing the engine can't play
+    /// back is accepted, like fields which are too small or overly long updates.
+    pub fn parse(content: &str) -> Result<Replay, String> {
+        let mut lines = content.lines().enumerate().map(|(lid, l)| (lid + 1, l.trim()));
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            Some((_, OUTDATED_HEADER)) => {
+                return Err("The replay was recorded by an older version of the game".to_string())
+            }
+            _ => return Err(format!("Expected '{}' in the first line", HEADER)),
+        }
+
+        let mut seed = None;
+        let mut daily = None;
+        // Replays of older versions only knew the classic mode
+        let mut mode = GameMode::Classic;
+        let mut movement = Movement::Direct;
+        let mut field = None;
+        let mut outcome = None;
+        let mut inputs = Vec::new();
+        for (lid, line) in lines {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let kind = tokens.next().unwrap_or("");
+            let values: Vec<&str> = tokens.collect();
+            let err = || format!("Line {}: invalid '{}'", lid, line);
+            let scalars = || -> Result<Vec<Scalar>, String> {
+                values.iter().map(|v| v.parse::<Scalar>().map_err(|_| err())).collect()
+            };
+            match (kind, values.len()) {
+                ("seed", 1) => seed = Some(try!(values[0].parse::<u64>().map_err(|_| err()))),
+                ("daily", 1) => daily = Some(try!(Date::parse(values[0]).ok_or_else(err))),
+                ("mode", 1) => mode = try!(GameMode::from_name(values[0]).ok_or_else(err)),
+                ("movement", _) => {
+                    movement = try!(Movement::parse(&values.join(" ")).ok_or_else(err))
+                }
+                ("field", 2) => {
+                    let v = try!(scalars());
+                    // The engine can't set up smaller fields
+                    if !v.iter().all(|v| v.is_finite() && *v >= MIN_FIELD_SIZE) {
+                        return Err(format!("Line {}: the field must be at least {} wide and high",
+                                           lid,
+                                           MIN_FIELD_SIZE));
+                    }
+                    field = Some([v[0], v[1]]);
+                }
+                ("outcome", 2) => {
+                    outcome = Some(Outcome {
+                        score: try!(values[0].parse::<u32>().map_err(|_| err())),
+                        ticks: try!(values[1].parse::<usize>().map_err(|_| err())),
+                    })
+                }
+                ("u", 1) => {
+                    let dt = try!(scalars())[0];
+                    if !(dt > 0.0 && dt <= MAX_UPDATE_TIME) {
+                        return Err(err());
+                    }
+                    inputs.push(Input::Update(dt));
+                }
+                ("p", 2) => {
+                    let v = try!(scalars());
+                    if !v.iter().all(|v| v.is_finite()) {
+                        return Err(err());
+                    }
+                    inputs.push(Input::HunterPos([v[0], v[1]]));
+                }
+                ("f", 1) => {
+                    inputs.push(Input::HunterForce(match values[0] {
+                        "1" => true,
+                        "0" => false,
+                        _ => return Err(err()),
+                    }))
+                }
+                _ => return Err(err()),
+            }
+        }
+
+        match (seed, field) {
+            (Some(seed), Some(field)) => {
+                if let Some(date) = daily {
+                    if date.seed() != seed {
+                        return Err(format!("The seed differs from the one of the daily \
+                                            challenge of {}",
+                                           date));
+                    }
+                }
+                Ok(Replay {
+                    seed: seed,
+                    daily: daily,
+                    mode: mode,
+                    movement: movement,
+                    field: field,
+                    outcome: outcome,
+                    inputs: inputs,
+                })
+            }
+            _ => Err("Both 'seed' and 'field' are required".to_string()),
+        }
+    }
+
+    /// Serialize the replay into a line-based format, with one input per line.
+    /// Numbers are written such that they parse back to exactly the same value.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "{}", HEADER).unwrap();
+        writeln!(out, "seed {}", self.seed).unwrap();
+        if let Some(date) = self.daily {
+            writeln!(out, "daily {}", date).unwrap();
+        }
+        writeln!(out, "mode {}", self.mode.name()).unwrap();
+        writeln!(out, "movement {}", self.movement.to_text()).unwrap();
+        writeln!(out, "field {:?} {:?}", self.field[0], self.field[1]).unwrap();
+        if let Some(outcome) = self.outcome {
+            writeln!(out, "outcome {} {}", outcome.score, outcome.ticks).unwrap();
+        }
+        for input in &self.inputs {
+            match *input {
+                Input::Update(dt) => writeln!(out, "u {:?}", dt),
+                Input::HunterPos(pos) => writeln!(out, "p {:?} {:?}", pos[0], pos[1]),
+                Input::HunterForce(enabled) => writeln!(out, "f {}", enabled as u8),
+            }
+            .unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{State, Scalar, Movement, Spring};
+    use engine::Engine;
+    use daily::Date;
+
+    /// Plays a game with scripted inputs for up to `steps` updates, returning the
+    /// engine and the last state
+    fn play(seed: u64, steps: usize) -> (Engine, State) {
+        let mut engine = Engine::from_seed([800.0, 600.0], seed);
+        for step in 0..steps {
+            let t = step as Scalar / 60.0;
+            engine.set_hunter_pos([400.0 + 300.0 * t.cos(), 300.0 + 200.0 * (t * 1.3).sin()]);
+            engine.set_hunter_force(step % 120 > 90);
+            if let Err(state) = engine.update(1.0 / 60.0) {
+                return (engine, state);
+            }
+        }
+        let state = engine.state().clone().unwrap();
+        (engine, state)
+    }
+
+    #[test]
+    fn record_and_simulate() {
+        let (engine, last) = play(42, 600);
+        let replay = engine.replay();
+        assert_eq!(replay.seed, 42);
+        assert!(replay.duration() > 0.0);
+        assert_eq!(Replay::parse(&replay.to_text()).as_ref(), Ok(replay));
+
+        // The same inputs with the same seed lead to the same game
+        let mut simulated = None;
+        replay.simulate(|_, s| simulated = Some(s.clone()));
+        assert_eq!(simulated, Some(last));
+        assert!(play(43, 600).1 != play(42, 600).1);
+
+        // Daily challenges are tagged with their date, which has to match the seed
+        let mut daily = replay.clone();
+        daily.daily = Some(Date::parse("2016-10-18").unwrap());
+        assert!(Replay::parse(&daily.to_text()).is_err());
+        daily.seed = daily.daily.unwrap().seed();
+        assert_eq!(Replay::parse(&daily.to_text()), Ok(daily));
+
+        // The settings of inertial movement are kept exactly, as they change the game
+        let mut inertial = replay.clone();
+        inertial.movement = Movement::Inertial(Spring {
+            stiffness: 42.5,
+            damping: 0.1,
+            max_speed: 1.0 / 3.0,
+        });
+        assert_eq!(Replay::parse(&inertial.to_text()).as_ref(), Ok(&inertial));
+        assert!(Replay::parse(&inertial.to_text().replace("inertial", "sluggish")).is_err());
+
+        assert!(Replay::parse("seed 1\nfield 320 480").is_err());
+        assert!(Replay::parse("catchit-replay 2\nseed 1").is_err());
+        assert!(Replay::parse("catchit-replay 2\nseed 1\nfield 320 480\nu x").is_err());
+        assert!(Replay::parse("catchit-replay 1\nseed 1\nfield 320 480").is_err());
+        assert!(Replay::parse("catchit-replay 2\nseed 1\nfield 320 480").is_ok());
+
+        // Nothing the engine can't play back is accepted
+        for invalid in &["field 1 2",
+                         "field inf 480",
+                         "field 320 480\nu NaN",
+                         "field 320 480\nu 0",
+                         "field 320 480\nu -0.01",
+                         "field 320 480\nu 1e12",
+                         "field 320 480\nmovement inertial 60 -1 2",
+                         "field 320 480\nmovement inertial 60 11 inf",
+                         "field 320 480\nmovement inertial NaN 11 2",
+                         "field 320 480\np NaN 10",
+                         "field 320 480\np 10 -inf"] {
+            let text = format!("catchit-replay 2\nseed 1\n{}", invalid);
+            assert!(Replay::parse(&text).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn verify() {
+        let (engine, last) = play(42, 100000);
+        let replay = engine.replay().clone();
+        let outcome = replay.outcome.unwrap();
+        assert_eq!(outcome.score, last.score);
+        assert_eq!(outcome.ticks, replay.ticks());
+        let verified = verify_replay(&Replay::parse(&replay.to_text()).unwrap()).unwrap();
+        assert_eq!((verified.score, verified.ticks), (last.score, outcome.ticks));
+        assert_eq!(verified.time, replay.duration());
+
+        let mut tampered = replay.clone();
+        tampered.outcome = Some(Outcome { score: last.score + 10, ..outcome });
+        assert_eq!(verify_replay(&tampered),
+                   Err(VerifyError::ScoreMismatch {
+                       claimed: last.score + 10,
+                       actual: last.score,
+                   }));
+        tampered.outcome = Some(Outcome { ticks: outcome.ticks + 1, ..outcome });
+        assert!(verify_replay(&tampered).is_err());
+        tampered.outcome = None;
+        assert_eq!(verify_replay(&tampered), Err(VerifyError::Unclaimed));
+
+        let mut cut = replay.clone();
+        cut.inputs.pop();
+        assert_eq!(verify_replay(&cut), Err(VerifyError::NotOver));
+        let mut extended = replay.clone();
+        extended.inputs.push(Input::Update(0.1));
+        assert_eq!(verify_replay(&extended), Err(VerifyError::InputsAfterGameOver));
+    }
+}
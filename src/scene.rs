@@ -0,0 +1,444 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use catchit::{Engine, State, Extent, Position, Theme, Renderer, Hud, SoftwareRenderer, Ghost,
+              Date, GameMode, Level, Achievement, Achievements, HUD_SPACE};
+
+use piston_window::{Button, Key};
+use graphics::math::Scalar;
+
+use audio::{Audio, AudioSink, NullSink};
+use config;
+use input::Bindings;
+use leaderboard_client::submit_score;
+use particles::Particles;
+use prefs::Preferences;
+use scores::HighScores;
+use sound::RodioSink;
+use {WIDTH, HEIGHT};
+
+const BINDINGS_FILE_NAME: &'static str = "bindings.cfg";
+const HIGH_SCORES_FILE_NAME: &'static str = "highscores.txt";
+const PREFERENCES_FILE_NAME: &'static str = "preferences.cfg";
+const THEMES_DIR_NAME: &'static str = "themes";
+const THEME_EXTENSION: &'static str = "theme";
+const LEVELS_DIR_NAME: &'static str = "levels";
+const LEVEL_EXTENSION: &'static str = "toml";
+const CAMPAIGN_SCORES_FILE_NAME: &'static str = "highscores_campaign.txt";
+const SCREENSHOTS_DIR_NAME: &'static str = "screenshots";
+pub const REPLAYS_DIR_NAME: &'static str = "replays";
+const DAILY_DIR_NAME: &'static str = "daily";
+const ACHIEVEMENTS_FILE_NAME: &'static str = "achievements.txt";
+const LINE_SPACING: Scalar = 1.4;
+/// Seconds a toast stays on screen
+const TOAST_DURATION: f64 = 3.0;
+/// Seconds of play which can be rewound, and are shown again after the game
+const REWIND_DURATION: f64 = 10.0;
+
+/// What should happen to the stack of scenes after a scene handled an event
+pub enum SceneChange {
+    /// Keep the current scene
+    Stay,
+    /// Put the given scene on top of the current one, which will be visible
+    /// again once the new scene is popped.
+    Push(Box<Scene>),
+    /// Replace the current scene with the given one
+    Replace(Box<Scene>),
+    /// Remove the current scene, revealing the previous one
+    Pop,
+    /// Remove all scenes and start over with the given one
+    Reset(Box<Scene>),
+    /// Remove all scenes, which ends the application
+    Quit,
+}
+
+/// A screen of the application, like the title or the game itself.
+///
+/// Only the top-most scene receives events and gets rendered.
+pub trait Scene {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer);
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange;
+
+    fn on_release(&mut self, _shared: &mut Shared, _button: Button) -> SceneChange {
+        SceneChange::Stay
+    }
+
+    fn on_mouse(&mut self, _shared: &mut Shared, _pos: Position) {}
+
+    fn update(&mut self, _shared: &mut Shared, _dt: f64) -> SceneChange {
+        SceneChange::Stay
+    }
+}
+
+/// A short message shown on top of all scenes for a while
+struct Toast {
+    text: String,
+    time_left: f64,
+}
+
+/// Data used by all scenes, which outlives each one of them
+pub struct Shared {
+    pub engine: Engine,
+    pub field: Extent,
+    pub bindings: Bindings,
+    /// The best scores of the current game mode
+    pub high_scores: HighScores,
+    pub particles: Particles,
+    pub prefs: Preferences,
+    /// All available themes, built-in ones first
+    pub themes: Vec<Theme>,
+    /// Index into `themes`
+    pub theme: usize,
+    pub audio: Audio<Box<AudioSink>>,
+    pub tries: u32,
+    /// The recorded game the player races against, which is kept for all following games
+    pub ghost: Option<Ghost>,
+    /// The day whose challenge is played and its best scores, if the player
+    /// chose the daily challenge
+    pub daily: Option<(Date, HighScores)>,
+    /// The levels of the campaign, built-in ones first
+    pub levels: Vec<Level>,
+    /// True if the player chose to play the campaign
+    pub campaign: bool,
+    pub achievements: Achievements,
+    toasts: Vec<Toast>,
+}
+
+impl Shared {
+    pub fn new(field: Extent) -> Shared {
+        let mut engine = Engine::from_field(field);
+        engine.set_rewind_duration(REWIND_DURATION);
+        let prefs = Preferences::load(&config::config_dir().join(PREFERENCES_FILE_NAME));
+        let sink: Box<AudioSink> = match RodioSink::new() {
+            Some(sink) => Box::new(sink),
+            None => {
+                println!("No audio device found - playing without sound");
+                Box::new(NullSink)
+            }
+        };
+        let themes = load_themes();
+        let theme = themes.iter().position(|t| t.name == prefs.theme).unwrap_or(0);
+        Shared {
+            engine: engine,
+            field: field,
+            bindings: Bindings::load(&config::config_dir().join(BINDINGS_FILE_NAME)),
+            high_scores: HighScores::load(&high_scores_path(GameMode::Classic)),
+            particles: Particles::new(),
+            audio: Audio::new(sink, prefs.volume.clone()),
+            prefs: prefs,
+            themes: themes,
+            theme: theme,
+            tries: 0,
+            ghost: None,
+            daily: None,
+            levels: load_levels(),
+            campaign: false,
+            achievements: config::read_file(&config::config_dir().join(ACHIEVEMENTS_FILE_NAME))
+                .map(|content| Achievements::parse(&content))
+                .unwrap_or_default(),
+            toasts: Vec::new(),
+        }
+    }
+
+    pub fn save_bindings(&self) {
+        if let Err(err) = self.bindings.save(&config::config_dir().join(BINDINGS_FILE_NAME)) {
+            println!("Could not save bindings: {}", err);
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+
+    /// Make changed preferences effective, and persist them
+    pub fn apply_preferences(&mut self) {
+        self.audio.volume = self.prefs.volume.clone();
+        self.prefs.theme = self.theme().name.clone();
+        if let Err(err) = self.prefs.save(&config::config_dir().join(PREFERENCES_FILE_NAME)) {
+            println!("Could not save preferences: {}", err);
+        }
+    }
+
+    /// Play by the rules of `mode` from the next game on
+    pub fn set_mode(&mut self, mode: GameMode) {
+        if mode != self.engine.mode() {
+            self.engine.set_mode(mode);
+            self.high_scores = HighScores::load(&high_scores_path(mode));
+        }
+    }
+
+    /// Play the daily challenge of `date` from now on, or regular games if it is `None`
+    pub fn set_daily(&mut self, date: Option<Date>) {
+        self.daily = date.map(|date| (date, HighScores::load(&daily_scores_path(date))));
+    }
+
+    pub fn campaign_scores(&self) -> HighScores {
+        HighScores::load(&config::config_dir().join(CAMPAIGN_SCORES_FILE_NAME))
+    }
+
+    /// Count the try and remember the score and replay of the finished game.
+    /// Scores of daily challenges go to the board of their day, and the replay is
+    /// submitted to the leaderboard server if there is one. Games which were
+    /// rewound, slowed down or stepped through are practice, and only their replay
+    /// is kept.
+    pub fn record_game_over(&mut self, state: &State) {
+        self.tries += 1;
+        if self.engine.is_practice() {
+            if !self.campaign {
+                self.save_replay(format!("{}-{}-practice", unix_time(), state.score));
+            }
+            return;
+        }
+        if self.campaign {
+            // Replays don't contain the levels, and couldn't be played back
+            let mut scores = self.campaign_scores();
+            if scores.insert(state.score).is_some() {
+                if let Err(err) = scores.save(&config::config_dir().join(CAMPAIGN_SCORES_FILE_NAME)) {
+                    println!("Could not save campaign scores: {}", err);
+                }
+            }
+            return;
+        }
+        let mut name = format!("{}-{}", unix_time(), state.score);
+        match self.daily {
+            Some((date, ref mut scores)) => {
+                if scores.insert(state.score).is_some() {
+                    if let Err(err) = scores.save(&daily_scores_path(date)) {
+                        println!("Could not save daily scores: {}", err);
+                    }
+                }
+                name.push_str(&format!("-daily-{}", date));
+            }
+            None => {
+                if self.high_scores.insert(state.score).is_some() {
+                    if let Err(err) = self.high_scores.save(&high_scores_path(state.mode)) {
+                        println!("Could not save high scores: {}", err);
+                    }
+                }
+            }
+        }
+
+        self.save_replay(name);
+
+        if let Some(ref addr) = self.prefs.leaderboard {
+            // The server plays the whole game again, which shouldn't stall ours
+            let (addr, player, replay) = (addr.clone(),
+                                          self.prefs.player.clone(),
+                                          self.engine.replay().clone());
+            thread::spawn(move || match submit_score(&addr, &player, &replay) {
+                Ok(entry) => println!("Submitted to the leaderboard: {}", entry.trim()),
+                Err(err) => println!("Could not submit to the leaderboard: {}", err),
+            });
+        }
+    }
+
+    fn save_replay(&self, name: String) {
+        let path = config::config_dir().join(REPLAYS_DIR_NAME).join(format!("{}.replay", name));
+        if let Err(err) = config::write_file(&path, self.engine.replay().to_text().as_bytes()) {
+            println!("Could not save replay: {}", err);
+        }
+    }
+
+    /// Announce and persist the given, newly unlocked achievements
+    pub fn on_unlocked(&mut self, unlocked: Vec<&'static Achievement>) {
+        if unlocked.is_empty() {
+            return;
+        }
+        for achievement in unlocked {
+            self.toasts.push(Toast {
+                text: format!("Achievement unlocked: {}", achievement.name),
+                time_left: TOAST_DURATION,
+            });
+        }
+        let path = config::config_dir().join(ACHIEVEMENTS_FILE_NAME);
+        if let Err(err) = config::write_file(&path, self.achievements.to_text().as_bytes()) {
+            println!("Could not save achievements: {}", err);
+        }
+    }
+
+    pub fn update_toasts(&mut self, dt: f64) {
+        for toast in &mut self.toasts {
+            toast.time_left -= dt;
+        }
+        self.toasts.retain(|t| t.time_left > 0.0);
+    }
+
+    /// Draws the current toasts at the top of the screen, on top of everything else
+    pub fn draw_toasts(&self, r: &mut Renderer) {
+        let lines: Vec<String> = self.toasts.iter().map(|t| t.text.clone()).collect();
+        let y = r.text_height() * 2.0;
+        draw_centered(r, self.theme(), &lines, y);
+    }
+
+    /// Information for the HUD, which is drawn below the playing field
+    pub fn hud(&self) -> Hud {
+        Hud {
+            size: [WIDTH as Scalar, HEIGHT as Scalar],
+            field: self.field,
+            best_score: match self.daily {
+                Some((_, ref scores)) => scores.best(),
+                None => self.high_scores.best(),
+            },
+            tries: self.tries,
+            ghost_score: self.ghost.as_ref().map(|g| g.score()),
+        }
+    }
+
+    /// Draws the hunter of the recorded game, if the player races against one
+    pub fn draw_ghost(&self, r: &mut Renderer) {
+        if let Some(hunter) = self.ghost.as_ref().and_then(|g| g.hunter()) {
+            r.draw_ghost(hunter, self.theme());
+        }
+    }
+
+    /// Draws the HUD, showing information about the given game if there is one
+    pub fn draw_hud(&self, s: Option<&State>, r: &mut Renderer) {
+        r.draw_hud(s, &self.hud(), self.theme());
+    }
+}
+
+/// Where the best scores of the given mode are stored
+fn high_scores_path(mode: GameMode) -> PathBuf {
+    let name = match mode {
+        GameMode::Classic => HIGH_SCORES_FILE_NAME.to_string(),
+        _ => format!("highscores_{}.txt", mode.name()),
+    };
+    config::config_dir().join(name)
+}
+
+/// Where the best scores of the daily challenge of `date` are stored
+pub fn daily_scores_path(date: Date) -> PathBuf {
+    config::config_dir().join(DAILY_DIR_NAME).join(format!("{}.txt", date))
+}
+
+/// Seconds since the unix epoch, to name files in the order they were written
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Draws `lines` centered on screen, starting at the given vertical position
+pub fn draw_centered(r: &mut Renderer, theme: &Theme, lines: &[String], y: Scalar) {
+    for (lid, line) in lines.iter().enumerate() {
+        let x = (WIDTH as Scalar - r.text_width(line)) / 2.0;
+        let y = y + lid as Scalar * r.text_height() * LINE_SPACING;
+        r.text(line, [x, y], theme.foreground);
+    }
+}
+
+/// Draws `lines` left-aligned below the given title
+pub fn draw_page(r: &mut Renderer, theme: &Theme, title: &str, lines: &[String]) {
+    let x = WIDTH as Scalar * HUD_SPACE;
+    let mut y = HEIGHT as Scalar * HUD_SPACE;
+    let line_height = r.text_height() * LINE_SPACING;
+    r.text(title, [x, y], theme.foreground);
+    y += line_height;
+    for line in lines {
+        y += line_height;
+        r.text(line, [x, y], theme.foreground);
+    }
+}
+
+/// Renders `scene` into a PNG file within our configuration directory, with
+/// `pixels_per_point` controlling its resolution
+pub fn save_screenshot(scene: &mut Scene,
+                       shared: &mut Shared,
+                       text_height: Scalar,
+                       pixels_per_point: Scalar) {
+    let size = [WIDTH as Scalar, HEIGHT as Scalar];
+    let mut r = SoftwareRenderer::new((size[0] * pixels_per_point).round() as u32,
+                                      (size[1] * pixels_per_point).round() as u32,
+                                      size);
+    r.text_height = text_height;
+    r.clear(shared.theme().background);
+    scene.render(shared, &mut r);
+
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let path = config::config_dir()
+        .join(SCREENSHOTS_DIR_NAME)
+        .join(format!("catchit-{}.png", secs));
+    match config::write_file(&path, &r.image().to_png()) {
+        Ok(()) => println!("Saved screenshot to '{}'", path.display()),
+        Err(err) => println!("Could not save screenshot: {}", err),
+    }
+}
+
+/// A vertical list of items, navigated with Up, Down and Return
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Menu {
+    pub selected: usize,
+}
+
+impl Menu {
+    /// Returns the index of the chosen item if Return was pressed
+    pub fn on_press(&mut self, button: Button, num_items: usize) -> Option<usize> {
+        match button {
+            Button::Keyboard(Key::Up) => {
+                self.selected = (self.selected + num_items - 1) % num_items;
+                None
+            }
+            Button::Keyboard(Key::Down) => {
+                self.selected = (self.selected + 1) % num_items;
+                None
+            }
+            Button::Keyboard(Key::Return) => Some(self.selected),
+            _ => None,
+        }
+    }
+
+    /// Returns the given items as lines, marking the selected one
+    pub fn lines<S: AsRef<str>>(&self, items: &[S]) -> Vec<String> {
+        items.iter()
+            .enumerate()
+            .map(|(iid, item)| {
+                format!("{} {}",
+                        if iid == self.selected { ">" } else { " " },
+                        item.as_ref())
+            })
+            .collect()
+    }
+}
+
+/// Returns the built-in levels, followed by all valid levels found in our
+/// configuration directory
+fn load_levels() -> Vec<Level> {
+    let mut levels = Level::builtin();
+    let mut paths: Vec<_> = match fs::read_dir(config::config_dir().join(LEVELS_DIR_NAME)) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return levels,
+    };
+    paths.sort();
+    for path in paths {
+        if path.extension().and_then(|e| e.to_str()) != Some(LEVEL_EXTENSION) {
+            continue;
+        }
+        match config::read_file(&path).map_err(|e| e.to_string()).and_then(|c| Level::parse(&c)) {
+            Ok(level) => levels.push(level),
+            Err(err) => println!("Ignoring level at '{}': {}", path.display(), err),
+        }
+    }
+    levels
+}
+
+/// Returns the built-in themes, followed by all valid themes found in our
+/// configuration directory
+fn load_themes() -> Vec<Theme> {
+    let mut themes = Theme::builtin();
+    let mut paths: Vec<_> = match fs::read_dir(config::config_dir().join(THEMES_DIR_NAME)) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return themes,
+    };
+    paths.sort();
+    for path in paths {
+        if path.extension().and_then(|e| e.to_str()) != Some(THEME_EXTENSION) {
+            continue;
+        }
+        match config::read_file(&path).map_err(|e| e.to_string()).and_then(|c| Theme::parse(&c)) {
+            Ok(theme) => themes.push(theme),
+            Err(err) => println!("Ignoring theme at '{}': {}", path.display(), err),
+        }
+    }
+    themes
+}
@@ -0,0 +1,740 @@
+use std::fs;
+
+use catchit::{State, Position, Renderer, Replay, Ghost, Date, GameMode, Movement, Spring,
+              Achievement, ScoreEntry, ScoreSource};
+
+use piston_window::{Button, Key};
+use graphics::math::Scalar;
+
+use config;
+use prefs::Preferences;
+use input::{Action, BindingsEditor, EditorResult};
+use scene::{Scene, SceneChange, Shared, Menu, REPLAYS_DIR_NAME, daily_scores_path,
+            draw_centered, draw_page};
+use scores;
+use HEIGHT;
+
+/// How fast the game runs in slow motion, relative to real time
+const SLOW_MOTION_SCALE: f64 = 0.25;
+/// Seconds of play undone per second the rewind button is held
+const REWIND_SPEED: f64 = 2.0;
+/// Seconds the end of the instant replay is held before it starts over
+const INSTANT_REPLAY_HOLD: f64 = 1.0;
+
+fn is_back(shared: &Shared, button: &Button) -> bool {
+    *button == Button::Keyboard(Key::Escape) ||
+    shared.bindings.action(button) == Some(Action::Quit)
+}
+
+/// Sets up a new game, which uses the seed of the ghost or of the daily challenge
+/// if there is one, or the levels of the campaign. Races against a ghost use its
+/// movement instead of the preferred one. Every game starts in real time.
+fn start_game(shared: &mut Shared) {
+    if let Some(mode) = shared.ghost.as_ref().map(|g| g.replay().mode) {
+        shared.set_mode(mode);
+    }
+    let movement = shared.ghost.as_ref().map_or(shared.prefs.movement, |g| g.replay().movement);
+    shared.engine.set_movement(movement);
+    shared.engine.set_time_scale(1.0);
+    shared.engine.set_step_mode(false);
+    shared.achievements.start_game();
+    if let Some(ref mut ghost) = shared.ghost {
+        ghost.restart();
+        shared.engine.reset_with_seed(shared.field, ghost.replay().seed);
+    } else if let Some((date, _)) = shared.daily {
+        shared.engine.reset_daily(shared.field, date);
+    } else if shared.campaign {
+        shared.engine.start_campaign(shared.field, shared.levels.clone());
+    } else {
+        shared.engine.reset(shared.field);
+    }
+    shared.particles.clear();
+}
+
+fn new_game(shared: &mut Shared) -> SceneChange {
+    start_game(shared);
+    SceneChange::Replace(Box::new(Playing::default()))
+}
+
+/// The kinds of games which can be started from the title
+enum GameKind {
+    /// A game by the rules of the chosen mode
+    Regular,
+    /// The daily challenge of the given day
+    Daily(Date),
+    /// The built-in levels, followed by the player's own ones
+    Campaign,
+}
+
+/// Starts a new game of the given kind without a ghost. Daily challenges and the
+/// campaign are always played by the classic rules.
+fn fresh_game(shared: &mut Shared, kind: GameKind) -> SceneChange {
+    shared.ghost = None;
+    shared.campaign = false;
+    shared.set_daily(None);
+    match kind {
+        GameKind::Regular => {}
+        GameKind::Daily(date) => {
+            shared.set_mode(GameMode::Classic);
+            shared.set_daily(Some(date));
+        }
+        GameKind::Campaign => {
+            shared.set_mode(GameMode::Classic);
+            shared.campaign = true;
+        }
+    }
+    new_game(shared)
+}
+
+/// The first scene, offering to play or to visit all other scenes
+#[derive(Default)]
+pub struct Title {
+    menu: Menu,
+}
+
+const TITLE_ITEMS: &'static [&'static str] = &["Play", "Daily Challenge", "Campaign", "Mode",
+                                               "Settings", "High Scores", "Achievements",
+                                               "Replays", "Quit"];
+const TITLE_MODE_ITEM: usize = 3;
+
+impl Title {
+    fn items(shared: &Shared) -> Vec<String> {
+        TITLE_ITEMS.iter()
+            .enumerate()
+            .map(|(item, name)| if item == TITLE_MODE_ITEM {
+                format!("{} < {} >", name, shared.engine.mode().description())
+            } else {
+                name.to_string()
+            })
+            .collect()
+    }
+
+    /// Switch to the next or previous game mode, depending on `step`
+    fn change_mode(shared: &mut Shared, step: isize) {
+        let modes = GameMode::all();
+        let current = modes.iter().position(|&m| m == shared.engine.mode()).unwrap_or(0);
+        let num_modes = modes.len() as isize;
+        shared.set_mode(modes[((current as isize + step + num_modes) % num_modes) as usize]);
+    }
+}
+
+impl Scene for Title {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        let mut lines = vec!["catchit".to_string(), String::new()];
+        lines.extend(self.menu.lines(&Self::items(shared)));
+        draw_centered(r, shared.theme(), &lines, HEIGHT as Scalar / 3.0);
+        shared.draw_hud(None, r);
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        match shared.bindings.action(&button) {
+            Some(Action::NewGame) => return fresh_game(shared, GameKind::Regular),
+            Some(Action::Quit) => return SceneChange::Quit,
+            _ => {}
+        }
+        if self.menu.selected == TITLE_MODE_ITEM {
+            match button {
+                Button::Keyboard(Key::Left) => Self::change_mode(shared, -1),
+                Button::Keyboard(Key::Right) => Self::change_mode(shared, 1),
+                _ => {}
+            }
+        }
+        match self.menu.on_press(button, TITLE_ITEMS.len()) {
+            Some(0) => fresh_game(shared, GameKind::Regular),
+            Some(1) => fresh_game(shared, GameKind::Daily(Date::today())),
+            Some(2) => fresh_game(shared, GameKind::Campaign),
+            Some(TITLE_MODE_ITEM) => {
+                Self::change_mode(shared, 1);
+                SceneChange::Stay
+            }
+            Some(4) => SceneChange::Push(Box::new(Settings::default())),
+            Some(5) => SceneChange::Push(Box::new(HighScores::new(shared))),
+            Some(6) => SceneChange::Push(Box::new(AchievementList)),
+            Some(7) => SceneChange::Push(Box::new(ReplayBrowser::new())),
+            Some(_) => SceneChange::Quit,
+            None => SceneChange::Stay,
+        }
+    }
+}
+
+/// The game itself
+#[derive(Default)]
+pub struct Playing {
+    /// Set while the rewind button is held
+    rewinding: bool,
+}
+
+impl Scene for Playing {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        if let Some(s) = shared.engine.state().as_ref() {
+            r.draw_state(s, shared.theme());
+        }
+        shared.draw_ghost(r);
+        shared.particles.draw(r);
+        shared.draw_hud(shared.engine.state().as_ref(), r);
+
+        let notice = if self.rewinding {
+            format!("Rewinding - release {} to play on",
+                    shared.bindings.describe(Action::Rewind))
+        } else if shared.engine.step_mode() {
+            format!("Frozen - press {} to step, {} to continue",
+                    shared.bindings.describe(Action::Step),
+                    shared.bindings.describe(Action::Freeze))
+        } else if shared.engine.time_scale() != 1.0 {
+            format!("Slow motion x{}", shared.engine.time_scale())
+        } else {
+            return;
+        };
+        let y = r.text_height() * 2.0;
+        draw_centered(r, shared.theme(), &[notice], y);
+    }
+
+    fn update(&mut self, shared: &mut Shared, dt: f64) -> SceneChange {
+        if self.rewinding {
+            shared.engine.rewind(dt * REWIND_SPEED);
+            let time = shared.engine.state().as_ref().map_or(0.0, |s| s.time);
+            if let Some(ref mut ghost) = shared.ghost {
+                ghost.seek(time);
+            }
+            shared.particles.update(dt);
+            return SceneChange::Stay;
+        }
+        let result = shared.engine.update(dt);
+        // Everything else follows the game, which is slower in slow motion
+        let dt = shared.engine.advanced();
+        if let Some(ref mut ghost) = shared.ghost {
+            ghost.update(dt);
+        }
+        for event in shared.engine.events() {
+            shared.particles.on_event(event, &shared.themes[shared.theme]);
+            shared.audio.on_event(event);
+        }
+        shared.particles.update(dt);
+        let unlocked = match (&result, shared.engine.state()) {
+            (&Err(ref state), _) |
+            (&Ok(()), &Some(ref state)) => {
+                shared.achievements
+                    .observe(shared.engine.events(), state, shared.engine.is_practice())
+            }
+            (&Ok(()), &None) => Vec::new(),
+        };
+        shared.on_unlocked(unlocked);
+
+        match result {
+            Ok(()) => SceneChange::Stay,
+            Err(state) => {
+                shared.record_game_over(&state);
+                let history = shared.engine.history().into_iter().cloned().collect();
+                SceneChange::Replace(Box::new(GameOver::new(state, history)))
+            }
+        }
+    }
+
+    fn on_mouse(&mut self, shared: &mut Shared, pos: Position) {
+        // The hunter is where it was back then, until play goes on
+        if !self.rewinding {
+            shared.engine.set_hunter_pos(pos);
+        }
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        match shared.bindings.action(&button) {
+            Some(Action::Force) => {
+                shared.engine.set_hunter_force(true);
+                shared.audio.on_force(true);
+                SceneChange::Stay
+            }
+            Some(Action::Pause) => {
+                shared.engine.set_hunter_force(false);
+                SceneChange::Push(Box::new(Paused::default()))
+            }
+            Some(Action::EditBindings) => {
+                shared.engine.set_hunter_force(false);
+                SceneChange::Push(Box::new(Settings::default()))
+            }
+            Some(Action::SlowMotion) => {
+                let scale = if shared.engine.time_scale() == 1.0 {
+                    SLOW_MOTION_SCALE
+                } else {
+                    1.0
+                };
+                shared.engine.set_time_scale(scale);
+                SceneChange::Stay
+            }
+            Some(Action::Freeze) => {
+                let frozen = shared.engine.step_mode();
+                shared.engine.set_step_mode(!frozen);
+                SceneChange::Stay
+            }
+            Some(Action::Step) => {
+                // Stepping freezes the game first, if it isn't already
+                if !shared.engine.step_mode() {
+                    shared.engine.set_step_mode(true);
+                }
+                shared.engine.request_step();
+                SceneChange::Stay
+            }
+            Some(Action::Rewind) => {
+                shared.engine.set_hunter_force(false);
+                shared.audio.on_force(false);
+                self.rewinding = true;
+                SceneChange::Stay
+            }
+            Some(Action::Quit) => SceneChange::Reset(Box::new(Title::default())),
+            _ => SceneChange::Stay,
+        }
+    }
+
+    fn on_release(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        match shared.bindings.action(&button) {
+            Some(Action::Force) => {
+                shared.engine.set_hunter_force(false);
+                shared.audio.on_force(false);
+            }
+            Some(Action::Rewind) => self.rewinding = false,
+            _ => {}
+        }
+        SceneChange::Stay
+    }
+}
+
+/// Shows the frozen game until the player continues
+#[derive(Default)]
+pub struct Paused {
+    menu: Menu,
+}
+
+const PAUSED_ITEMS: &'static [&'static str] = &["Continue", "Settings", "Quit to title"];
+
+impl Scene for Paused {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        if let Some(s) = shared.engine.state().as_ref() {
+            r.draw_state(s, shared.theme());
+        }
+        shared.draw_ghost(r);
+        shared.particles.draw(r);
+        shared.draw_hud(shared.engine.state().as_ref(), r);
+
+        let mut lines = vec![format!("Paused - press {} to continue",
+                                     shared.bindings.describe(Action::Pause)),
+                             String::new()];
+        lines.extend(self.menu.lines(PAUSED_ITEMS));
+        draw_centered(r, shared.theme(), &lines, HEIGHT as Scalar / 3.0);
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        if shared.bindings.action(&button) == Some(Action::Pause) || is_back(shared, &button) {
+            return SceneChange::Pop;
+        }
+        match self.menu.on_press(button, PAUSED_ITEMS.len()) {
+            Some(0) => SceneChange::Pop,
+            Some(1) => SceneChange::Push(Box::new(Settings::default())),
+            Some(_) => SceneChange::Reset(Box::new(Title::default())),
+            None => SceneChange::Stay,
+        }
+    }
+}
+
+/// Shows the last state of a finished game
+pub struct GameOver {
+    state: State,
+    /// The last seconds of the game, ending with its last state, shown over and over
+    instant_replay: Vec<State>,
+    /// Seconds into the instant replay
+    replay_time: f64,
+    menu: Menu,
+}
+
+const GAME_OVER_ITEMS: &'static [&'static str] = &["New game", "High Scores", "Settings",
+                                                   "Back to title"];
+
+impl GameOver {
+    /// `history` holds the states before the last one, oldest first
+    pub fn new(state: State, history: Vec<State>) -> GameOver {
+        let mut instant_replay = history;
+        instant_replay.push(state.clone());
+        GameOver {
+            state: state,
+            instant_replay: instant_replay,
+            replay_time: 0.0,
+            menu: Menu::default(),
+        }
+    }
+
+    /// The state of the instant replay at the current time
+    fn replayed_state(&self) -> &State {
+        let start = self.instant_replay[0].time;
+        let time = start + self.replay_time % (self.state.time - start + INSTANT_REPLAY_HOLD);
+        self.instant_replay.iter().rev().find(|s| s.time <= time).unwrap_or(&self.state)
+    }
+}
+
+/// Sums up how the score of a game came about
+fn score_breakdown(s: &State) -> Vec<String> {
+    let mut lines = Vec::new();
+    let prey: Vec<&ScoreEntry> = s.ledger.iter().filter(|e| e.source == ScoreSource::Prey).collect();
+    if let Some(best) = prey.iter().max_by_key(|e| e.points) {
+        lines.push(format!("{} prey caught for {} points",
+                           prey.len(),
+                           prey.iter().map(|e| e.points).sum::<u32>()));
+        lines.push(format!("Best catch: {} points after {:.0}s, multiplied by {:.2}",
+                           best.points,
+                           best.time,
+                           best.multiplier()));
+    }
+    let alive: u32 = s.ledger
+        .iter()
+        .filter(|e| e.source == ScoreSource::Survival)
+        .map(|e| e.points)
+        .sum();
+    if alive > 0 {
+        lines.push(format!("Staying alive earned {} points", alive));
+    }
+    lines
+}
+
+impl Scene for GameOver {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        r.draw_state(self.replayed_state(), shared.theme());
+        shared.draw_ghost(r);
+        shared.particles.draw(r);
+        shared.draw_hud(Some(&self.state), r);
+
+        let time_up = self.state.mode.time_limit().map_or(false, |limit| self.state.time >= limit);
+        let won = self.state.wave.as_ref().map_or(false, |w| w.is_won());
+        let mut lines = vec![format!("{} - press {} for new game",
+                                     if won {
+                                         "Campaign complete"
+                                     } else if time_up {
+                                         "Time's up"
+                                     } else {
+                                         "Game Over"
+                                     },
+                                     shared.bindings.describe(Action::NewGame)),
+                             format!("Use {} for repelling force",
+                                     shared.bindings.describe(Action::Force)),
+                             String::new()];
+        if let Some((date, _)) = shared.daily {
+            lines.insert(0, format!("Daily Challenge {}", date));
+        }
+        let breakdown = score_breakdown(&self.state);
+        if !breakdown.is_empty() {
+            lines.extend(breakdown);
+            lines.push(String::new());
+        }
+        lines.extend(self.menu.lines(GAME_OVER_ITEMS));
+        draw_centered(r, shared.theme(), &lines, HEIGHT as Scalar / 3.0);
+    }
+
+    fn update(&mut self, shared: &mut Shared, dt: f64) -> SceneChange {
+        self.replay_time += dt;
+        shared.particles.update(dt);
+        SceneChange::Stay
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        if is_back(shared, &button) {
+            return SceneChange::Replace(Box::new(Title::default()));
+        }
+        match shared.bindings.action(&button) {
+            Some(Action::NewGame) => return new_game(shared),
+            Some(Action::EditBindings) => return SceneChange::Push(Box::new(Settings::default())),
+            _ => {}
+        }
+        match self.menu.on_press(button, GAME_OVER_ITEMS.len()) {
+            Some(0) => new_game(shared),
+            Some(1) => SceneChange::Push(Box::new(HighScores::new(shared))),
+            Some(2) => SceneChange::Push(Box::new(Settings::default())),
+            Some(_) => SceneChange::Replace(Box::new(Title::default())),
+            None => SceneChange::Stay,
+        }
+    }
+}
+
+/// Allows to change how the game is controlled, and how it looks and sounds
+#[derive(Default)]
+pub struct Settings {
+    menu: Menu,
+    bindings_editor: Option<BindingsEditor>,
+}
+
+const SETTINGS_BINDINGS_ITEM: usize = 0;
+const SETTINGS_VOLUME_ITEMS: &'static [&'static str] = &["Master volume", "Effects volume",
+                                                         "Music volume"];
+const SETTINGS_THEME_ITEM: usize = 4;
+const SETTINGS_MOVEMENT_ITEM: usize = 5;
+const VOLUME_STEP: f32 = 0.1;
+
+impl Settings {
+    fn items(shared: &Shared) -> Vec<String> {
+        let mut items = vec!["Key bindings".to_string()];
+        let v = &shared.prefs.volume;
+        for (name, volume) in SETTINGS_VOLUME_ITEMS.iter().zip(&[v.master, v.effects, v.music]) {
+            items.push(format!("{:<16} < {:>3}% >", name, (volume * 100.0).round()));
+        }
+        items.push(format!("{:<16} < {} >", "Theme", shared.theme().name));
+        items.push(format!("{:<16} < {} >", "Movement", shared.prefs.movement.description()));
+        items.push("Back".to_string());
+        items
+    }
+
+    /// Returns the volume controlled by the given menu item, if it is a volume item
+    fn volume_mut(prefs: &mut Preferences, item: usize) -> Option<&mut f32> {
+        match item {
+            1 => Some(&mut prefs.volume.master),
+            2 => Some(&mut prefs.volume.effects),
+            3 => Some(&mut prefs.volume.music),
+            _ => None,
+        }
+    }
+
+    fn leave(shared: &mut Shared) -> SceneChange {
+        shared.apply_preferences();
+        SceneChange::Pop
+    }
+}
+
+impl Scene for Settings {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        match self.bindings_editor {
+            Some(ref editor) => {
+                let menu = Menu { selected: editor.selected };
+                let items: Vec<_> = Action::all()
+                    .iter()
+                    .enumerate()
+                    .map(|(aid, action)| {
+                        let buttons = if aid == editor.selected && editor.capturing {
+                            "<press a button>".to_string()
+                        } else {
+                            shared.bindings.describe(*action)
+                        };
+                        format!("{:<16} {}", action.description(), buttons)
+                    })
+                    .collect();
+                draw_page(r,
+                          shared.theme(),
+                          "Up/Down: select, Return: add button, Backspace: clear, Escape: done",
+                          &menu.lines(&items));
+            }
+            None => {
+                let lines = self.menu.lines(&Self::items(shared));
+                draw_page(r,
+                          shared.theme(),
+                          "Settings - use Left/Right to change values",
+                          &lines);
+            }
+        }
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        if let Some(mut editor) = self.bindings_editor.take() {
+            match editor.on_press(button, &mut shared.bindings) {
+                EditorResult::Continue => self.bindings_editor = Some(editor),
+                EditorResult::Close => shared.save_bindings(),
+            }
+            return SceneChange::Stay;
+        }
+
+        if is_back(shared, &button) {
+            return Self::leave(shared);
+        }
+
+        let step = match button {
+            Button::Keyboard(Key::Left) => -1,
+            Button::Keyboard(Key::Right) => 1,
+            _ => 0,
+        };
+        if step != 0 {
+            if self.menu.selected == SETTINGS_THEME_ITEM {
+                let num_themes = shared.themes.len() as isize;
+                shared.theme = ((shared.theme as isize + step + num_themes) % num_themes) as usize;
+            } else if self.menu.selected == SETTINGS_MOVEMENT_ITEM {
+                shared.prefs.movement = match shared.prefs.movement {
+                    Movement::Direct => Movement::Inertial(Spring::default()),
+                    Movement::Inertial(_) => Movement::Direct,
+                };
+            } else if let Some(volume) = Self::volume_mut(&mut shared.prefs, self.menu.selected) {
+                *volume = (*volume + step as f32 * VOLUME_STEP).max(0.0).min(1.0);
+            }
+            shared.audio.volume = shared.prefs.volume.clone();
+            return SceneChange::Stay;
+        }
+
+        let num_items = Self::items(shared).len();
+        match self.menu.on_press(button, num_items) {
+            Some(SETTINGS_BINDINGS_ITEM) => {
+                self.bindings_editor = Some(BindingsEditor::new());
+                SceneChange::Stay
+            }
+            Some(item) if item + 1 == num_items => Self::leave(shared),
+            _ => SceneChange::Stay,
+        }
+    }
+}
+
+/// Lists the best scores so far, and those of today's daily challenge
+pub struct HighScores {
+    today: Date,
+    daily: Vec<u32>,
+    campaign_best: u32,
+}
+
+impl HighScores {
+    pub fn new(shared: &Shared) -> HighScores {
+        let today = Date::today();
+        HighScores {
+            today: today,
+            daily: scores::HighScores::load(&daily_scores_path(today)).scores().to_vec(),
+            campaign_best: shared.campaign_scores().best(),
+        }
+    }
+}
+
+fn score_lines(scores: &[u32]) -> Vec<String> {
+    let mut lines: Vec<_> = scores.iter()
+        .enumerate()
+        .map(|(rank, score)| format!("{:>2}. {:>8}", rank + 1, score))
+        .collect();
+    if lines.is_empty() {
+        lines.push("No games played yet".to_string());
+    }
+    lines
+}
+
+impl Scene for HighScores {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        let mut lines = score_lines(shared.high_scores.scores());
+        lines.push(String::new());
+        lines.push(format!("Daily Challenge {}", self.today));
+        lines.push(String::new());
+        lines.extend(score_lines(&self.daily));
+        lines.push(String::new());
+        lines.push(format!("Best campaign score: {}", self.campaign_best));
+        draw_page(r,
+                  shared.theme(),
+                  &format!("High Scores - {}", shared.engine.mode().description()),
+                  &lines);
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        if is_back(shared, &button) || button == Button::Keyboard(Key::Return) {
+            SceneChange::Pop
+        } else {
+            SceneChange::Stay
+        }
+    }
+}
+
+/// Lists all achievements, and whether they were unlocked
+pub struct AchievementList;
+
+impl Scene for AchievementList {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        let mut lines = Vec::new();
+        for achievement in Achievement::all() {
+            lines.push(format!("[{}] {}",
+                               if shared.achievements.is_unlocked(achievement) { "x" } else { " " },
+                               achievement.name));
+            lines.push(format!("    {}", achievement.description));
+        }
+        draw_page(r,
+                  shared.theme(),
+                  &format!("Achievements - {} of {} unlocked",
+                           shared.achievements.unlocked().len(),
+                           Achievement::all().len()),
+                  &lines);
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        if is_back(shared, &button) || button == Button::Keyboard(Key::Return) {
+            SceneChange::Pop
+        } else {
+            SceneChange::Stay
+        }
+    }
+}
+
+/// Lists the replays stored in our configuration directory, and starts a race
+/// against the chosen one
+pub struct ReplayBrowser {
+    replays: Vec<String>,
+    menu: Menu,
+    /// Why the chosen replay can't be raced against
+    error: Option<String>,
+}
+
+impl ReplayBrowser {
+    pub fn new() -> ReplayBrowser {
+        let mut replays: Vec<String> = fs::read_dir(config::config_dir().join(REPLAYS_DIR_NAME))
+            .map(|entries| {
+                entries.filter_map(|e| e.ok())
+                    .filter_map(|e| e.file_name().into_string().ok())
+                    .collect()
+            })
+            .unwrap_or_else(|_| Vec::new());
+        replays.sort();
+        ReplayBrowser {
+            replays: replays,
+            menu: Menu::default(),
+            error: None,
+        }
+    }
+
+    fn load_ghost(shared: &Shared, name: &str) -> Result<Ghost, String> {
+        let path = config::config_dir().join(REPLAYS_DIR_NAME).join(name);
+        let content = try!(config::read_file(&path).map_err(|e| e.to_string()));
+        let replay = try!(Replay::parse(&content));
+        if replay.field != shared.field {
+            return Err("It was recorded with a different window size".to_string());
+        }
+        let mut ghost = Ghost::new(replay);
+        ghost.set_rewind_duration(shared.engine.rewind_duration());
+        Ok(ghost)
+    }
+}
+
+impl Scene for ReplayBrowser {
+    fn render(&mut self, shared: &mut Shared, r: &mut Renderer) {
+        let mut lines = if self.replays.is_empty() {
+            vec!["No replays recorded yet".to_string()]
+        } else {
+            self.menu.lines(&self.replays)
+        };
+        if let Some(ref err) = self.error {
+            lines.push(String::new());
+            lines.push(format!("Can't race against this replay: {}", err));
+        }
+        draw_page(r,
+                  shared.theme(),
+                  "Replays - press Return to race against a ghost",
+                  &lines);
+    }
+
+    fn on_press(&mut self, shared: &mut Shared, button: Button) -> SceneChange {
+        if is_back(shared, &button) {
+            return SceneChange::Pop;
+        }
+        if self.replays.is_empty() {
+            return SceneChange::Stay;
+        }
+        self.error = None;
+        match self.menu.on_press(button, self.replays.len()) {
+            Some(item) => {
+                match Self::load_ghost(shared, &self.replays[item]) {
+                    Ok(ghost) => {
+                        shared.ghost = Some(ghost);
+                        shared.set_daily(None);
+                        shared.campaign = false;
+                        start_game(shared);
+                        SceneChange::Reset(Box::new(Playing::default()))
+                    }
+                    Err(err) => {
+                        self.error = Some(err);
+                        SceneChange::Stay
+                    }
+                }
+            }
+            None => SceneChange::Stay,
+        }
+    }
+}
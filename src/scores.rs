@@ -0,0 +1,53 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use config;
+
+const MAX_HIGH_SCORES: usize = 10;
+
+/// The best scores ever achieved, best one first
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HighScores {
+    scores: Vec<u32>,
+}
+
+impl HighScores {
+    /// Load scores from the given file, one score per line. Unparseable lines
+    /// are ignored, and a missing file yields no scores.
+    pub fn load(path: &Path) -> HighScores {
+        let mut hs = HighScores::default();
+        if let Ok(content) = config::read_file(path) {
+            for score in content.lines().filter_map(|l| l.trim().parse().ok()) {
+                hs.insert(score);
+            }
+        }
+        hs
+    }
+
+    pub fn save(&self, path: &Path) -> ::std::io::Result<()> {
+        let mut out = String::new();
+        for score in &self.scores {
+            writeln!(out, "{}", score).unwrap();
+        }
+        config::write_file(path, out.as_bytes())
+    }
+
+    /// Insert the given score, and return its rank if it made it into the list
+    pub fn insert(&mut self, score: u32) -> Option<usize> {
+        let rank = self.scores.iter().position(|&s| score > s).unwrap_or(self.scores.len());
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        self.scores.insert(rank, score);
+        self.scores.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    pub fn best(&self) -> u32 {
+        self.scores.first().cloned().unwrap_or(0)
+    }
+
+    pub fn scores(&self) -> &[u32] {
+        &self.scores
+    }
+}
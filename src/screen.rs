@@ -0,0 +1,59 @@
+use piston_window::RenderArgs;
+use graphics::math::Scalar;
+
+/// Maps the logical point space the game is played in to the window, and the
+/// window to physical pixels.
+///
+/// All game coordinates, including the playing field, are logical points. They
+/// are scaled uniformly to fit into the window, and centered within it.
+/// Physical pixels only matter when rasterizing text, which would otherwise
+/// look blurry on HiDPI displays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screen {
+    /// Size of the logical space in points, which is the same on every display
+    pub logical: [Scalar; 2],
+    /// Position of the logical space within the window, in window points
+    pub offset: [Scalar; 2],
+    /// Window points per logical point
+    pub zoom: Scalar,
+    /// Physical pixels per window point, which is greater than 1 on HiDPI displays
+    pub dpi_scale: Scalar,
+}
+
+impl Screen {
+    pub fn new(logical: [Scalar; 2]) -> Screen {
+        Screen {
+            logical: logical,
+            offset: [0.0, 0.0],
+            zoom: 1.0,
+            dpi_scale: 1.0,
+        }
+    }
+
+    /// Adjust to the current window size and pixel density
+    pub fn update(&mut self, args: &RenderArgs) {
+        if args.width == 0 || args.height == 0 {
+            return;
+        }
+        let window = [args.width as Scalar, args.height as Scalar];
+        self.zoom = (window[0] / self.logical[0]).min(window[1] / self.logical[1]);
+        self.offset = [(window[0] - self.logical[0] * self.zoom) / 2.0,
+                       (window[1] - self.logical[1] * self.zoom) / 2.0];
+        self.dpi_scale = args.draw_width as Scalar / args.width as Scalar;
+    }
+
+    /// Physical pixels per logical point
+    pub fn pixels_per_point(&self) -> Scalar {
+        self.zoom * self.dpi_scale
+    }
+
+    /// Converts a position in window points, like the mouse cursor, into logical points
+    pub fn to_logical(&self, window_pos: [Scalar; 2]) -> [Scalar; 2] {
+        [(window_pos[0] - self.offset[0]) / self.zoom, (window_pos[1] - self.offset[1]) / self.zoom]
+    }
+
+    /// The font size in pixels to rasterize text of the given logical size with
+    pub fn font_size(&self, points: u32) -> u32 {
+        (points as Scalar * self.pixels_per_point()).round().max(1.0) as u32
+    }
+}
@@ -0,0 +1,347 @@
+use types::{Extent, Scalar, Position};
+use theme::Color;
+use image::Image;
+use render::Renderer;
+
+/// Glyphs are 3 by 5 cells, with one empty column between them
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+/// Returns the rows of the given character, top to bottom, with the most significant
+/// of the 3 bits being the leftmost cell. Lowercase letters are drawn as uppercase
+/// ones, and unknown characters as a box.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0, 0, 0, 0, 0],
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 3, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 1, 2, 2],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        '.' => [0, 0, 0, 0, 2],
+        ',' => [0, 0, 0, 2, 4],
+        ':' => [0, 2, 0, 2, 0],
+        '-' => [0, 0, 7, 0, 0],
+        '+' => [0, 2, 7, 2, 0],
+        '/' => [1, 1, 2, 4, 4],
+        '%' => [5, 1, 2, 4, 5],
+        '!' => [2, 2, 2, 0, 2],
+        '?' => [6, 1, 2, 0, 2],
+        '(' => [1, 2, 2, 2, 1],
+        ')' => [4, 2, 2, 2, 4],
+        '<' => [1, 2, 4, 2, 1],
+        '>' => [4, 2, 1, 2, 4],
+        '=' => [0, 7, 0, 7, 0],
+        '\'' => [2, 2, 0, 0, 0],
+        _ => [7, 5, 5, 5, 7],
+    }
+}
+
+/// Renders into an RGBA `Image` on the CPU, without any graphics hardware.
+///
+/// Like the window, the logical space is scaled uniformly to fit the image and
+/// centered within it. Pixels are either covered or not, without anti-aliasing,
+/// so the output only depends on the input and is the same on every machine.
+/// Text uses a small built-in bitmap font.
+pub struct SoftwareRenderer {
+    image: Image,
+    /// Pixels per logical point
+    zoom: Scalar,
+    /// Position of the logical space within the image, in pixels
+    offset: [Scalar; 2],
+    /// Height of capital letters in logical points
+    pub text_height: Scalar,
+}
+
+impl SoftwareRenderer {
+    /// A renderer drawing the logical space of the given `size` into an image of
+    /// `width` by `height` pixels
+    pub fn new(width: u32, height: u32, size: Extent) -> SoftwareRenderer {
+        let zoom = (width as Scalar / size[0]).min(height as Scalar / size[1]);
+        SoftwareRenderer {
+            image: Image::new(width, height),
+            zoom: zoom,
+            offset: [(width as Scalar - size[0] * zoom) / 2.0,
+                     (height as Scalar - size[1] * zoom) / 2.0],
+            text_height: 10.0,
+        }
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    fn to_pixels(&self, pos: Position) -> [Scalar; 2] {
+        [self.offset[0] + pos[0] * self.zoom, self.offset[1] + pos[1] * self.zoom]
+    }
+
+    /// Converts a position in pixels into logical points
+    pub fn to_logical(&self, pixel: [Scalar; 2]) -> Position {
+        [(pixel[0] - self.offset[0]) / self.zoom, (pixel[1] - self.offset[1]) / self.zoom]
+    }
+
+    /// Calls `covered` with the center of every pixel within the rectangle `[x, y, w, h]`
+    /// given in pixels, and blends `color` into the ones it returns true for
+    fn fill<F>(&mut self, rect: [Scalar; 4], color: Color, covered: F)
+        where F: Fn(Scalar, Scalar) -> bool
+    {
+        let (x0, y0) = (rect[0].floor() as i64, rect[1].floor() as i64);
+        let (x1, y1) = ((rect[0] + rect[2]).ceil() as i64, (rect[1] + rect[3]).ceil() as i64);
+        for y in y0.max(0)..y1.min(self.image.height as i64) {
+            for x in x0.max(0)..x1.min(self.image.width as i64) {
+                if covered(x as Scalar + 0.5, y as Scalar + 0.5) {
+                    self.image.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn pixel_rect(&self, rect: [Scalar; 4]) -> [Scalar; 4] {
+        let pos = self.to_pixels([rect[0], rect[1]]);
+        [pos[0], pos[1], rect[2] * self.zoom, rect[3] * self.zoom]
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn clear(&mut self, color: Color) {
+        self.image.fill(color);
+    }
+
+    fn rectangle(&mut self, rect: [Scalar; 4], color: Color) {
+        let r = self.pixel_rect(rect);
+        self.fill(r, color, |x, y| {
+            x >= r[0] && x < r[0] + r[2] && y >= r[1] && y < r[1] + r[3]
+        });
+    }
+
+    fn ellipse(&mut self, rect: [Scalar; 4], color: Color) {
+        let r = self.pixel_rect(rect);
+        let (rx, ry) = (r[2] / 2.0, r[3] / 2.0);
+        let center = [r[0] + rx, r[1] + ry];
+        self.fill(r, color, |x, y| {
+            let (dx, dy) = ((x - center[0]) / rx, (y - center[1]) / ry);
+            dx * dx + dy * dy <= 1.0
+        });
+    }
+
+    fn line(&mut self, from: Position, to: Position, width: Scalar, color: Color) {
+        let (a, b) = (self.to_pixels(from), self.to_pixels(to));
+        // Lines are at least one pixel wide, so they never vanish when zoomed out
+        let half_width = (width * self.zoom / 2.0).max(0.5);
+        let d = [b[0] - a[0], b[1] - a[1]];
+        let len_sq = d[0] * d[0] + d[1] * d[1];
+        let bounds = [a[0].min(b[0]) - half_width,
+                      a[1].min(b[1]) - half_width,
+                      d[0].abs() + half_width * 2.0,
+                      d[1].abs() + half_width * 2.0];
+        self.fill(bounds, color, |x, y| {
+            let t = if len_sq == 0.0 {
+                0.0
+            } else {
+                (((x - a[0]) * d[0] + (y - a[1]) * d[1]) / len_sq).max(0.0).min(1.0)
+            };
+            let (dx, dy) = (x - (a[0] + t * d[0]), y - (a[1] + t * d[1]));
+            dx * dx + dy * dy <= half_width * half_width
+        });
+    }
+
+    fn text(&mut self, text: &str, pos: Position, color: Color) {
+        let cell = self.text_height / GLYPH_HEIGHT as Scalar;
+        let top = pos[1] - self.text_height;
+        for (cid, c) in text.chars().enumerate() {
+            let left = pos[0] + (cid * GLYPH_ADVANCE) as Scalar * cell;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        self.rectangle([left + col as Scalar * cell,
+                                        top + row as Scalar * cell,
+                                        cell,
+                                        cell],
+                                       color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn text_width(&mut self, text: &str) -> Scalar {
+        let chars = text.chars().count();
+        if chars == 0 {
+            return 0.0;
+        }
+        let cell = self.text_height / GLYPH_HEIGHT as Scalar;
+        (chars * GLYPH_ADVANCE - 1) as Scalar * cell
+    }
+
+    fn text_height(&self) -> Scalar {
+        self.text_height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{Read, Write};
+    use std::path::PathBuf;
+
+    use super::*;
+    use types::{State, Object, Obstacle, ObstacleKind, CollisionShape, Hunter, GameMode};
+    use transition::Transition;
+    use render::{Hud, Renderer, FrameStats};
+    use theme::Theme;
+    use image::decode_png;
+
+    fn object(pos: Position, half_size: Scalar, shape: CollisionShape) -> Object {
+        Object {
+            pos: pos,
+            half_size: half_size,
+            shape: shape,
+        }
+    }
+
+    fn state() -> State {
+        let obstacle = |kind, pos, shape| {
+            Obstacle {
+                kind: kind,
+                object: object(pos, 6.0, shape),
+                velocity: [0.0, 0.0],
+                age: 0.0,
+                lifetime: None,
+                mass: 1.0,
+                base_speed: 0.0,
+                departing: false,
+            }
+        };
+        State {
+            mode: GameMode::Classic,
+            field: [320.0, 100.0],
+            hunter: Hunter {
+                object: object([80.0, 50.0], 8.0, CollisionShape::Circle),
+                force: 0.0,
+                velocity: [0.0, 0.0],
+                target: [80.0, 50.0],
+            },
+            prey: object([240.0, 30.0], 8.0, CollisionShape::Square),
+            obstacles: vec![obstacle(ObstacleKind::Deadly, [160.0, 20.0], CollisionShape::Square),
+                            obstacle(ObstacleKind::Deadly, [200.0, 80.0], CollisionShape::Circle),
+                            obstacle(ObstacleKind::AttractiveForceSwitch,
+                                     [40.0, 85.0],
+                                     CollisionShape::Circle),
+                            obstacle(ObstacleKind::InvisibiltySwitch,
+                                     [280.0, 70.0],
+                                     CollisionShape::Square)],
+            score: 42,
+            score_coeff: 1.5,
+            obstacle_opacity: Transition::new(1.0, 0.0, 1.0),
+            attracting_force: Transition::new(0.0, 1.0, 1.0),
+            last_dt: 0.0,
+            time: 0.0,
+            wave: None,
+            combo: 0,
+            combo_time_left: 0.0,
+            ledger: Vec::new(),
+        }
+    }
+
+    /// Compares the pixels of `img` to the PNG with the given name in `res/golden`,
+    /// independently of how the PNG was compressed.
+    /// With `CATCHIT_UPDATE_GOLDEN` set, the file is written instead.
+    fn check_golden(img: &Image, name: &str) {
+        let path = PathBuf::from(file!()).parent().unwrap().join("../res/golden").join(name);
+        if env::var_os("CATCHIT_UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(&path).unwrap().write_all(&img.to_png()).unwrap();
+            return;
+        }
+        let mut golden = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut golden).unwrap();
+        assert!(*img == decode_png(&golden),
+                "{} differs from its golden image, run with CATCHIT_UPDATE_GOLDEN=1 to update",
+                name);
+    }
+
+    #[test]
+    fn golden_images() {
+        let hud = Hud {
+            size: [320.0, 120.0],
+            field: [320.0, 100.0],
+            best_score: 100,
+            tries: 3,
+            ghost_score: None,
+        };
+        let s = state();
+
+        let mut r = SoftwareRenderer::new(320, 120, hud.size);
+        r.text_height = 5.0;
+        r.draw_scene(Some(&s), &hud, &Theme::classic());
+        check_golden(r.image(), "scene.png");
+
+        // Scaled down and centered vertically, using another theme
+        let mut r = SoftwareRenderer::new(160, 90, hud.size);
+        r.text_height = 5.0;
+        r.draw_scene(Some(&s), &hud, &Theme::builtin()[3]);
+        assert_eq!(r.image().pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(r.to_logical([80.0, 45.0]), [160.0, 60.0]);
+        check_golden(r.image(), "scene_scaled.png");
+
+        // A running combo adds its count and a bar showing the time left
+        let mut s = s;
+        s.combo = 3;
+        s.combo_time_left = 1.5;
+        let mut r = SoftwareRenderer::new(320, 120, hud.size);
+        r.text_height = 5.0;
+        r.draw_scene(Some(&s), &hud, &Theme::classic());
+        check_golden(r.image(), "scene_combo.png");
+
+        // The debug overlay outlines all objects, and shows what the engine is up to
+        s.hunter.force = 1.0;
+        s.hunter.velocity = [40.0, -20.0];
+        s.obstacles[1].velocity = [-60.0, 0.0];
+        let stats = FrameStats {
+            fps: 60.0,
+            update_time: 0.0004,
+        };
+        let mut r = SoftwareRenderer::new(320, 120, hud.size);
+        r.text_height = 5.0;
+        r.draw_scene(Some(&s), &hud, &Theme::classic());
+        r.draw_debug_overlay(Some(&s), &stats);
+        check_golden(r.image(), "scene_debug.png");
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::source::SineWave;
+
+use audio::{AudioSink, Sound};
+
+/// Base frequencies of the background melody, in Hz
+const MELODY: &'static [f32] = &[220.0, 261.63, 293.66, 329.63, 392.0, 329.63, 293.66, 261.63];
+const MUSIC_AMPLITUDE: f32 = 0.2;
+const CALM_NOTE_MS: f32 = 400.0;
+const FRANTIC_NOTE_MS: f32 = 120.0;
+/// Amount of notes to keep queued, to prevent gaps in the music
+const QUEUED_NOTES: usize = 2;
+
+/// Plays synthesized sounds on the default output device
+pub struct RodioSink {
+    // Keeps the output device open - sounds stop playing once it is dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    music: Sink,
+    next_note: usize,
+}
+
+impl RodioSink {
+    /// Returns None if there is no usable output device
+    pub fn new() -> Option<RodioSink> {
+        let (stream, handle) = match OutputStream::try_default() {
+            Ok(v) => v,
+            Err(_) => return None,
+        };
+        let music = match Sink::try_new(&handle) {
+            Ok(sink) => sink,
+            Err(_) => return None,
+        };
+        Some(RodioSink {
+            _stream: stream,
+            handle: handle,
+            music: music,
+            next_note: 0,
+        })
+    }
+}
+
+impl AudioSink for RodioSink {
+    fn play(&mut self, sound: Sound, volume: f32) {
+        let (freq, millis) = match sound {
+            Sound::PreyCaught => (880.0, 80),
+            Sound::SwitchHit => (660.0, 150),
+            Sound::ForceEnabled => (330.0, 60),
+            Sound::GameOver => (110.0, 600),
+        };
+        let source = SineWave::new(freq)
+            .take_duration(Duration::from_millis(millis))
+            .fade_in(Duration::from_millis(5))
+            .amplify(volume);
+        // Failing to play a sound effect is not worth bothering the player with
+        let _ = self.handle.play_raw(source);
+    }
+
+    fn set_music(&mut self, intensity: f32, volume: f32) {
+        self.music.set_volume(volume);
+        if volume == 0.0 {
+            return;
+        }
+        // Faster and higher notes the more intense it gets
+        let millis = CALM_NOTE_MS + (FRANTIC_NOTE_MS - CALM_NOTE_MS) * intensity;
+        while self.music.len() < QUEUED_NOTES {
+            let freq = MELODY[self.next_note % MELODY.len()] * (1.0 + intensity * 0.5);
+            self.next_note += 1;
+            self.music.append(SineWave::new(freq)
+                .take_duration(Duration::from_millis(millis as u64))
+                .fade_in(Duration::from_millis(10))
+                .amplify(MUSIC_AMPLITUDE));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Policies deciding where new obstacles appear.
+//!
+//! All of them try a bounded number of random candidates, and fall back to the
+//! best one they saw if none fulfilled their requirements. That way, spawning
+//! terminates even on fields too small for the requested distances.
+use rand::{Rng, XorShiftRng};
+use vecmath::{vec2_len, vec2_sub};
+
+use types::{State, Extent, Scalar, Pt, Position, Velocity};
+
+/// How many candidates a policy may try before falling back
+const MAX_ATTEMPTS: usize = 32;
+/// How many candidates `AwayFromHunterPolicy` compares
+const AWAY_FROM_HUNTER_CANDIDATES: usize = 8;
+/// The default `PoissonDiskPolicy::spacing`
+const DEFAULT_POISSON_DISK_SPACING: Scalar = 0.05;
+
+/// Decides where a new obstacle appears, and how it moves
+pub trait SpawnPolicy {
+    /// Returns position and velocity of a new obstacle with the given `half_size`,
+    /// which should be at least `min_distance` away from the hunter. `velocity` is
+    /// the randomly chosen one, which may be adjusted.
+    fn spawn(&self,
+             rng: &mut XorShiftRng,
+             s: &State,
+             half_size: Pt,
+             min_distance: Scalar,
+             velocity: Velocity)
+             -> (Position, Velocity);
+}
+
+pub fn clamp_to_field(field: &Extent, half_size: Pt, mut pos: Position) -> Position {
+    if pos[0] - half_size < 0.0 {
+        pos[0] = half_size;
+    }
+    if pos[0] + half_size > field[0] {
+        pos[0] = field[0] - half_size;
+    }
+    if pos[1] - half_size < 0.0 {
+        pos[1] = half_size;
+    }
+    if pos[1] + half_size > field[1] {
+        pos[1] = field[1] - half_size;
+    }
+    pos
+}
+
+/// A random position at which an object of the given size is fully within the field
+pub fn random_position(field: &Extent, half_size: Pt, rng: &mut XorShiftRng) -> Position {
+    clamp_to_field(field,
+                   half_size,
+                   [rng.gen_range(0.0, field[0]), rng.gen_range(0.0, field[1])])
+}
+
+fn hunter_distance(s: &State, pos: Position) -> Scalar {
+    vec2_len(vec2_sub(pos, s.hunter.object.pos))
+}
+
+/// Generates up to `MAX_ATTEMPTS` candidates, and returns the first one whose
+/// score isn't negative, or the best one if there is none
+fn first_acceptable<G, S>(mut generate: G, score: S) -> Position
+    where G: FnMut() -> Position,
+          S: Fn(Position) -> Scalar
+{
+    let mut best = generate();
+    let mut best_score = score(best);
+    for _ in 1..MAX_ATTEMPTS {
+        if best_score >= 0.0 {
+            break;
+        }
+        let pos = generate();
+        let pos_score = score(pos);
+        if pos_score > best_score {
+            best = pos;
+            best_score = pos_score;
+        }
+    }
+    best
+}
+
+/// Anywhere in the field, as long as it is far enough from the hunter.
+/// This is what the engine uses by default.
+#[derive(Debug, Clone, Default)]
+pub struct RandomPolicy;
+
+impl SpawnPolicy for RandomPolicy {
+    fn spawn(&self,
+             rng: &mut XorShiftRng,
+             s: &State,
+             _half_size: Pt,
+             min_distance: Scalar,
+             velocity: Velocity)
+             -> (Position, Velocity) {
+        // Positions are picked like those of the prey, to keep existing replays valid
+        let pos = first_acceptable(|| random_position(&s.field, s.prey.half_size, rng),
+                                   |pos| hunter_distance(s, pos) - min_distance);
+        (pos, velocity)
+    }
+}
+
+/// Obstacles enter from the edge of the field they move away from
+#[derive(Debug, Clone, Default)]
+pub struct EdgeEntryPolicy;
+
+impl SpawnPolicy for EdgeEntryPolicy {
+    fn spawn(&self,
+             rng: &mut XorShiftRng,
+             s: &State,
+             half_size: Pt,
+             min_distance: Scalar,
+             velocity: Velocity)
+             -> (Position, Velocity) {
+        let field = s.field;
+        // The axis on which the obstacle crosses the field faster decides the edge
+        let axis = if (velocity[0] / field[0]).abs() >= (velocity[1] / field[1]).abs() {
+            0
+        } else {
+            1
+        };
+        let edge = if velocity[axis] >= 0.0 {
+            half_size
+        } else {
+            field[axis] - half_size
+        };
+        let pos = first_acceptable(|| {
+                                       let mut pos = random_position(&field, half_size, rng);
+                                       pos[axis] = edge;
+                                       pos
+                                   },
+                                   |pos| hunter_distance(s, pos) - min_distance);
+        (pos, velocity)
+    }
+}
+
+/// The farthest from the hunter of a few random positions. More are tried while
+/// none is `min_distance` away, and the corner farthest from the hunter is used if
+/// all of them are too close.
+#[derive(Debug, Clone, Default)]
+pub struct AwayFromHunterPolicy;
+
+impl SpawnPolicy for AwayFromHunterPolicy {
+    fn spawn(&self,
+             rng: &mut XorShiftRng,
+             s: &State,
+             half_size: Pt,
+             min_distance: Scalar,
+             velocity: Velocity)
+             -> (Position, Velocity) {
+        let mut best = random_position(&s.field, half_size, rng);
+        for attempt in 1..MAX_ATTEMPTS {
+            if attempt >= AWAY_FROM_HUNTER_CANDIDATES && hunter_distance(s, best) >= min_distance {
+                break;
+            }
+            let pos = random_position(&s.field, half_size, rng);
+            if hunter_distance(s, pos) > hunter_distance(s, best) {
+                best = pos;
+            }
+        }
+        if hunter_distance(s, best) < min_distance {
+            let hunter = s.hunter.object.pos;
+            let corner = [if hunter[0] < s.field[0] / 2.0 { s.field[0] } else { 0.0 },
+                          if hunter[1] < s.field[1] / 2.0 { s.field[1] } else { 0.0 }];
+            best = clamp_to_field(&s.field, half_size, corner);
+        }
+        (best, velocity)
+    }
+}
+
+/// Keeps a gap to the prey and all other obstacles as well, so obstacles spread
+/// evenly across the field
+#[derive(Debug, Clone)]
+pub struct PoissonDiskPolicy {
+    /// The least gap between objects, relative to the shorter side of the field
+    pub spacing: Scalar,
+}
+
+impl Default for PoissonDiskPolicy {
+    fn default() -> PoissonDiskPolicy {
+        PoissonDiskPolicy { spacing: DEFAULT_POISSON_DISK_SPACING }
+    }
+}
+
+impl SpawnPolicy for PoissonDiskPolicy {
+    fn spawn(&self,
+             rng: &mut XorShiftRng,
+             s: &State,
+             half_size: Pt,
+             min_distance: Scalar,
+             velocity: Velocity)
+             -> (Position, Velocity) {
+        let gap = self.spacing * s.field[0].min(s.field[1]);
+        let clearance = |pos: Position| {
+            s.obstacles
+                .iter()
+                .map(|o| &o.object)
+                .chain(Some(&s.prey))
+                .map(|o| vec2_len(vec2_sub(pos, o.pos)) - o.half_size - half_size - gap)
+                .fold(hunter_distance(s, pos) - min_distance, Scalar::min)
+        };
+        let pos = first_acceptable(|| random_position(&s.field, half_size, rng), clearance);
+        (pos, velocity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, XorShiftRng};
+    use types::{Obstacle, Object, ObstacleKind, CollisionShape};
+    use engine::Engine;
+
+    #[test]
+    fn policies_terminate_and_stay_in_field() {
+        let mut s = Engine::from_seed([400.0, 400.0], 1).state().clone().unwrap();
+        s.hunter.object.pos = [200.0, 200.0];
+        for i in 0..20 {
+            s.obstacles.push(Obstacle {
+                kind: ObstacleKind::Deadly,
+                object: Object {
+                    pos: [i as Scalar * 20.0, 100.0],
+                    half_size: 3.0,
+                    shape: CollisionShape::Circle,
+                },
+                velocity: [0.0, 0.0],
+                age: 0.0,
+                lifetime: None,
+                mass: 1.0,
+                base_speed: 0.0,
+                departing: false,
+            });
+        }
+        let policies: Vec<Box<SpawnPolicy>> = vec![Box::new(RandomPolicy),
+                                                   Box::new(EdgeEntryPolicy),
+                                                   Box::new(AwayFromHunterPolicy),
+                                                   Box::new(PoissonDiskPolicy::default())];
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        for policy in &policies {
+            // Nothing in the field is that far away from the hunter
+            let (pos, _) = policy.spawn(&mut rng, &s, 3.0, 10000.0, [10.0, 0.0]);
+            assert!(pos[0] >= 3.0 && pos[0] <= 397.0 && pos[1] >= 3.0 && pos[1] <= 397.0);
+            assert!(hunter_distance(&s, pos) > 100.0);
+
+            let (pos, _) = policy.spawn(&mut rng, &s, 3.0, 50.0, [10.0, 0.0]);
+            assert!(hunter_distance(&s, pos) >= 50.0);
+        }
+
+        let (pos, _) = EdgeEntryPolicy.spawn(&mut rng, &s, 3.0, 50.0, [-10.0, 5.0]);
+        assert_eq!(pos[0], 397.0);
+
+        // Only a small area near the opposite corner is far enough away
+        s.hunter.object.pos = [10.0, 10.0];
+        for _ in 0..10 {
+            let (pos, _) = AwayFromHunterPolicy.spawn(&mut rng, &s, 3.0, 500.0, [0.0, 0.0]);
+            assert!(hunter_distance(&s, pos) >= 500.0);
+        }
+        s.hunter.object.pos = [200.0, 200.0];
+
+        let poisson = PoissonDiskPolicy::default();
+        for _ in 0..10 {
+            let (pos, _) = poisson.spawn(&mut rng, &s, 3.0, 50.0, [0.0, 0.0]);
+            assert!(s.obstacles.iter().all(|o| vec2_len(vec2_sub(pos, o.object.pos)) >= 26.0));
+        }
+    }
+}
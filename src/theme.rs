@@ -0,0 +1,189 @@
+use format::parse_pairs;
+
+/// [red, green, blue, alpha], each ranging from 0 to 1
+pub type Color = [f32; 4];
+
+/// The colours used to draw the game
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    /// Used for text, lines, and deadly obstacles
+    pub foreground: Color,
+    pub prey: Color,
+    pub hunter: Color,
+    /// The hunter's colour while the attracting force is active
+    pub hunter_attracting: Color,
+    pub attractive_switch: Color,
+    pub invisibility_switch: Color,
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> Color {
+    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]
+}
+
+/// Parses colours like `#ff8800` or `#ff880080`
+pub fn parse_color(s: &str) -> Option<Color> {
+    if !s.starts_with('#') || (s.len() != 7 && s.len() != 9) {
+        return None;
+    }
+    let mut c = [1.0; 4];
+    for (cid, chunk) in s.as_bytes()[1..].chunks(2).enumerate() {
+        let hex = match ::std::str::from_utf8(chunk) {
+            Ok(hex) => hex,
+            Err(_) => return None,
+        };
+        match u8::from_str_radix(hex, 16) {
+            Ok(v) => c[cid] = v as f32 / 255.0,
+            Err(_) => return None,
+        }
+    }
+    Some(c)
+}
+
+pub fn format_color(c: &Color) -> String {
+    let b = |v: f32| (v * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}{:02x}", b(c[0]), b(c[1]), b(c[2]), b(c[3]))
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::classic()
+    }
+}
+
+impl Theme {
+    /// The original look of the game
+    pub fn classic() -> Theme {
+        Theme {
+            name: "Classic".to_string(),
+            background: [1.0, 204.0 / 255.0, 0.0, 1.0],
+            foreground: [0.2, 0.2, 0.2, 1.0],
+            prey: [204.0 / 255.0, 0.0, 0.0, 1.0],
+            hunter: [204.0 / 255.0, 0.0, 0.0, 1.0],
+            hunter_attracting: [0.0, 0.0, 0.8, 1.0],
+            attractive_switch: [0.0, 0.0, 0.8, 1.0],
+            invisibility_switch: [0.8, 0.8, 0.8, 1.0],
+        }
+    }
+
+    /// All themes which come with the game, starting with the classic one.
+    ///
+    /// The colour-blind safe themes are based on the Okabe-Ito palette, which
+    /// keeps prey and switches apart for all common forms of colour blindness.
+    pub fn builtin() -> Vec<Theme> {
+        vec![Theme::classic(),
+             Theme {
+                 name: "Colour-blind safe".to_string(),
+                 background: rgb(0xf0, 0xe4, 0x42),
+                 foreground: rgb(0x20, 0x20, 0x20),
+                 prey: rgb(0xd5, 0x5e, 0x00),
+                 hunter: rgb(0xd5, 0x5e, 0x00),
+                 hunter_attracting: rgb(0x00, 0x72, 0xb2),
+                 attractive_switch: rgb(0x00, 0x72, 0xb2),
+                 invisibility_switch: rgb(0xff, 0xff, 0xff),
+             },
+             Theme {
+                 name: "Colour-blind safe dark".to_string(),
+                 background: rgb(0x20, 0x20, 0x20),
+                 foreground: rgb(0xe0, 0xe0, 0xe0),
+                 prey: rgb(0xe6, 0x9f, 0x00),
+                 hunter: rgb(0xe6, 0x9f, 0x00),
+                 hunter_attracting: rgb(0x56, 0xb4, 0xe9),
+                 attractive_switch: rgb(0x56, 0xb4, 0xe9),
+                 invisibility_switch: rgb(0xcc, 0x79, 0xa7),
+             },
+             Theme {
+                 name: "High contrast".to_string(),
+                 background: rgb(0x00, 0x00, 0x00),
+                 foreground: rgb(0xff, 0xff, 0xff),
+                 prey: rgb(0xff, 0xff, 0x00),
+                 hunter: rgb(0xff, 0xff, 0x00),
+                 hunter_attracting: rgb(0x00, 0xff, 0xff),
+                 attractive_switch: rgb(0x00, 0xff, 0xff),
+                 invisibility_switch: rgb(0xff, 0x00, 0xff),
+             },
+             Theme {
+                 name: "High contrast light".to_string(),
+                 background: rgb(0xff, 0xff, 0xff),
+                 foreground: rgb(0x00, 0x00, 0x00),
+                 prey: rgb(0xc0, 0x00, 0x00),
+                 hunter: rgb(0xc0, 0x00, 0x00),
+                 hunter_attracting: rgb(0x00, 0x00, 0xc0),
+                 attractive_switch: rgb(0x00, 0x00, 0xc0),
+                 invisibility_switch: rgb(0x00, 0x80, 0x00),
+             }]
+    }
+
+    /// Parses a theme from `key = value` lines, with colours given like `#rrggbb`.
+    /// Keys which are not given default to the classic theme.
+    pub fn parse(content: &str) -> Result<Theme, String> {
+        let pairs = try!(parse_pairs(content)
+            .map_err(|line| format!("Line {}: expected 'key = value'", line)));
+        let mut t = Theme::classic();
+        for (key, value) in pairs {
+            if key == "name" {
+                t.name = value;
+                continue;
+            }
+            let color = match &key[..] {
+                "background" => &mut t.background,
+                "foreground" => &mut t.foreground,
+                "prey" => &mut t.prey,
+                "hunter" => &mut t.hunter,
+                "hunter_attracting" => &mut t.hunter_attracting,
+                "attractive_switch" => &mut t.attractive_switch,
+                "invisibility_switch" => &mut t.invisibility_switch,
+                _ => return Err(format!("Unknown key: '{}'", key)),
+            };
+            *color = try!(parse_color(&value)
+                .ok_or_else(|| format!("Invalid colour for '{}': '{}'", key, value)));
+        }
+        Ok(t)
+    }
+
+    /// Serialize the theme in the format understood by `parse()`
+    pub fn to_config(&self) -> String {
+        format!("name = {}\nbackground = {}\nforeground = {}\nprey = {}\nhunter = {}\n\
+                 hunter_attracting = {}\nattractive_switch = {}\ninvisibility_switch = {}\n",
+                self.name,
+                format_color(&self.background),
+                format_color(&self.foreground),
+                format_color(&self.prey),
+                format_color(&self.hunter),
+                format_color(&self.hunter_attracting),
+                format_color(&self.attractive_switch),
+                format_color(&self.invisibility_switch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#ff0000"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_color("#00ff0000"), Some([0.0, 1.0, 0.0, 0.0]));
+        assert_eq!(parse_color("ff0000"), None);
+        assert_eq!(parse_color("#ff00"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+        assert_eq!(format_color(&[1.0, 0.0, 0.0, 1.0]), "#ff0000ff");
+    }
+
+    #[test]
+    fn parse_and_serialize() {
+        for theme in Theme::builtin() {
+            assert_eq!(Theme::parse(&theme.to_config()), Ok(theme));
+        }
+
+        let t = Theme::parse("# comment\nname = Mine\nprey = #00ff00").unwrap();
+        assert_eq!(t.name, "Mine");
+        assert_eq!(t.prey, [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(t.background, Theme::classic().background);
+
+        assert!(Theme::parse("prey = red").is_err());
+        assert!(Theme::parse("nonsense = #000000").is_err());
+        assert!(Theme::parse("no pairs here").is_err());
+    }
+}
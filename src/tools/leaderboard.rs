@@ -0,0 +1,404 @@
+//! Serves a leaderboard of verified scores over HTTP, see `catchit::Leaderboard`.
+//!
+//! All requests and responses are plain text:
+//!
+//! * `POST /scores?name=NAME` with a replay as body submits the game it recorded.
+//!   The game is played again, and only stored if it ends as the replay claims.
+//! * `GET /scores?mode=MODE&seed=SEED&limit=N` lists the best scores, one per line
+//!   as `rank score name mode seed id`. All parameters are optional.
+//! * `GET /replays/ID` returns the replay of a stored score.
+extern crate catchit;
+
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use catchit::{Leaderboard, LeaderboardEntry, Submission, GameMode};
+
+const USAGE: &'static str = "Usage: catchit-leaderboard [--addr HOST:PORT] [--dir PATH]";
+const DEFAULT_ADDR: &'static str = "127.0.0.1:7878";
+const DEFAULT_DIR: &'static str = "leaderboard";
+/// How many scores are listed if the query doesn't say
+const DEFAULT_LIMIT: usize = 10;
+/// The largest request body we accept, which fits replays of about a quarter hour
+const MAX_BODY_LEN: usize = 4 * 1024 * 1024;
+/// The most bytes read of the request line and headers together
+const MAX_HEAD_LEN: u64 = 16 * 1024;
+/// How long a connection may stall while reading the request or writing the response
+const TIMEOUT_SECS: u64 = 10;
+/// The most connections served at once, each in a thread of its own
+const MAX_CONNECTIONS: usize = 32;
+
+/// The inverse of percent-encoding, or None if the escapes are invalid
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut iter = value.bytes();
+    while let Some(b) = iter.next() {
+        bytes.push(match b {
+            b'%' => {
+                let hex: Vec<u8> = iter.by_ref().take(2).collect();
+                match String::from_utf8(hex).ok().and_then(|h| u8::from_str_radix(&h, 16).ok()) {
+                    Some(b) => b,
+                    None => return None,
+                }
+            }
+            b'+' => b' ',
+            _ => b,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// A request which panicked leaves the leaderboard as consistent as one which
+/// failed, so its lock is used regardless
+fn lock(board: &Mutex<Leaderboard>) -> MutexGuard<'_, Leaderboard> {
+    board.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// How an entry is listed in responses, as `rank score name mode seed id`
+fn listing_line(rank: usize, entry: &LeaderboardEntry) -> String {
+    format!("{} {} {} {} {} {}",
+            rank,
+            entry.score,
+            entry.name,
+            entry.mode.name(),
+            entry.seed,
+            entry.id)
+}
+
+/// Answers a request for the given method and target, like `/scores?mode=zen`,
+/// returning the HTTP status and the body of the response
+fn handle(board: &Mutex<Leaderboard>, method: &str, target: &str, body: &str) -> (u16, String) {
+    let (path, query) = match target.find('?') {
+        Some(pos) => (&target[..pos], &target[pos + 1..]),
+        None => (target, ""),
+    };
+    let params: Vec<(&str, String)> = query.split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.find('=') {
+            Some(pos) => (&p[..pos], percent_decode(&p[pos + 1..])),
+            None => (p, Some(String::new())),
+        })
+        .filter_map(|(key, value)| value.map(|v| (key, v)))
+        .collect();
+    let param = |key: &str| params.iter().find(|p| p.0 == key).map(|p| &p.1[..]);
+
+    match (method, path) {
+        ("POST", "/scores") => {
+            // Playing the game again takes a while, and other requests are served
+            // in the meantime
+            let submitted = Submission::verify(param("name").unwrap_or(""), body)
+                .and_then(|submission| {
+                    let mut board = lock(board);
+                    let entry = try!(board.submit(submission)).clone();
+                    let rank = board.query(Some(entry.mode), Some(entry.seed))
+                        .iter()
+                        .position(|e| e.id == entry.id)
+                        .unwrap() + 1;
+                    Ok(listing_line(rank, &entry))
+                });
+            match submitted {
+                Ok(line) => (201, format!("{}\n", line)),
+                Err(err) => (422, format!("{}\n", err)),
+            }
+        }
+        ("GET", "/scores") => {
+            let mode = match param("mode") {
+                Some(name) => {
+                    match GameMode::from_name(name) {
+                        Some(mode) => Some(mode),
+                        None => return (400, format!("Unknown mode '{}'\n", name)),
+                    }
+                }
+                None => None,
+            };
+            let seed = match param("seed").map(|s| s.parse::<u64>()) {
+                Some(Ok(seed)) => Some(seed),
+                Some(Err(_)) => return (400, "The seed must be a number\n".to_string()),
+                None => None,
+            };
+            let limit = match param("limit").map(|l| l.parse::<usize>()) {
+                Some(Ok(limit)) => limit,
+                Some(Err(_)) => return (400, "The limit must be a number\n".to_string()),
+                None => DEFAULT_LIMIT,
+            };
+            let mut out = String::new();
+            let board = lock(board);
+            for (rank, entry) in board.query(mode, seed).into_iter().take(limit).enumerate() {
+                writeln!(out, "{}", listing_line(rank + 1, entry)).unwrap();
+            }
+            (200, out)
+        }
+        ("GET", _) if path.starts_with("/replays/") => {
+            let id = path["/replays/".len()..].parse().ok();
+            match id.and_then(|id| lock(board).replay_text(id)) {
+                Some(text) => (200, text),
+                None => (404, "No such replay\n".to_string()),
+            }
+        }
+        (_, "/scores") => (405, "Only GET and POST are supported\n".to_string()),
+        _ => (404, format!("Nothing at '{}'\n", path)),
+    }
+}
+
+/// Reads one request from the given connection, and writes the response
+fn serve(board: &Mutex<Leaderboard>, stream: TcpStream) -> io::Result<()> {
+    let timeout = Some(Duration::from_secs(TIMEOUT_SECS));
+    try!(stream.set_read_timeout(timeout));
+    try!(stream.set_write_timeout(timeout));
+    let mut reader = BufReader::new(try!(stream.try_clone())).take(MAX_HEAD_LEN);
+    let mut request_line = String::new();
+    try!(reader.read_line(&mut request_line));
+    let mut content_len = 0;
+    loop {
+        let mut header = String::new();
+        if try!(reader.read_line(&mut header)) == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some(pos) = header.find(':') {
+            if header[..pos].trim().to_lowercase() == "content-length" {
+                content_len = header[pos + 1..].trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut tokens = request_line.split_whitespace();
+    let (status, body) = match (tokens.next(), tokens.next()) {
+        (Some(_), Some(_)) if content_len > MAX_BODY_LEN => {
+            (413, "The request is too large\n".to_string())
+        }
+        (Some(method), Some(target)) => {
+            let mut body = Vec::new();
+            reader.set_limit(content_len as u64);
+            try!(reader.read_to_end(&mut body));
+            if body.len() < content_len {
+                (400, "The body is shorter than its Content-Length\n".to_string())
+            } else {
+                match String::from_utf8(body) {
+                    Ok(body) => handle(board, method, target, &body),
+                    Err(_) => (400, "The body must be UTF-8\n".to_string()),
+                }
+            }
+        }
+        _ => (400, "Malformed request\n".to_string()),
+    };
+    write_response(stream, status, &body)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+fn write_response(mut stream: TcpStream, status: u16, body: &str) -> io::Result<()> {
+    try!(write!(stream,
+                "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                reason(status),
+                body.len()));
+    try!(stream.write_all(body.as_bytes()));
+    stream.flush()
+}
+
+/// Counts a connection as served for as long as it lives, even if serving it
+/// panics
+struct Connection {
+    active: Arc<AtomicUsize>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Serves every connection to `listener` in a thread of its own, so a slow or
+/// failing request doesn't hold up the others. Beyond `MAX_CONNECTIONS` at once,
+/// connections are turned away right away.
+fn accept(listener: TcpListener, board: Leaderboard) {
+    let board = Arc::new(Mutex::new(board));
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("Could not accept a connection: {}", err);
+                continue;
+            }
+        };
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(1)))
+                .and_then(|_| write_response(stream, 503, "Too many requests, try again later\n"));
+            continue;
+        }
+        let connection = Connection { active: active.clone() };
+        let board = board.clone();
+        thread::spawn(move || {
+            let _connection = connection;
+            if let Err(err) = serve(&board, stream) {
+                println!("Request failed: {}", err);
+            }
+        });
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut dir = DEFAULT_DIR.to_string();
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match &flag[..] {
+            "--addr" => addr = try!(args.next().ok_or(USAGE)),
+            "--dir" => dir = try!(args.next().ok_or(USAGE)),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let board = try!(Leaderboard::open(&dir[..])
+        .map_err(|e| format!("Could not open the leaderboard at '{}': {}", dir, e)));
+    let listener = try!(TcpListener::bind(&addr[..])
+        .map_err(|e| format!("Could not listen on '{}': {}", addr, e)));
+    println!("Serving the leaderboard in '{}' on http://{}", dir, addr);
+    accept(listener, board);
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        println!("catchit-leaderboard: {}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::net::Shutdown;
+    use catchit::{Engine, Replay};
+
+    /// A finished game with scripted inputs
+    fn finished_game(seed: u64) -> Replay {
+        let mut engine = Engine::from_seed([800.0, 600.0], seed);
+        for step in 0.. {
+            let t = step as f64 / 60.0;
+            engine.set_hunter_pos([400.0 + 300.0 * t.cos(), 300.0 + 200.0 * (t * 1.3).sin()]);
+            if engine.update(1.0 / 60.0).is_err() {
+                break;
+            }
+        }
+        engine.replay().clone()
+    }
+
+    /// Sends `head` and `body` as they are, and returns the status and body of
+    /// the response
+    fn request(addr: &str, head: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{}\r\n\r\n{}", head, body).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        (status, body.to_string())
+    }
+
+    #[test]
+    fn survives_bad_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let dir = env::temp_dir()
+            .join(format!("catchit-leaderboard-{}", listener.local_addr().unwrap().port()));
+        let _ = fs::remove_dir_all(&dir);
+        let board = Leaderboard::open(&dir).unwrap();
+        thread::spawn(move || accept(listener, board));
+
+        let game = finished_game(1);
+        let post = |name: &str, body: &str| {
+            request(&addr,
+                    &format!("POST /scores?name={} HTTP/1.1\r\nContent-Length: {}",
+                             name,
+                             body.len()),
+                    body)
+        };
+        let score = game.outcome.unwrap().score;
+        assert_eq!(post("alice", &game.to_text()),
+                   (201, format!("1 {} alice classic 1 1\n", score)));
+        assert_eq!(post("bob", &game.to_text()).0, 422);
+
+        // Neither replays the engine can't play, nor oversized or broken requests
+        // take the server down
+        let text = game.to_text();
+        assert_eq!(post("mallory", &text.replace("field 800.0 600.0", "field 1 2")).0, 422);
+        assert_eq!(post("mallory", &text.replacen("u 0.016666666666666666", "u 1e12", 1)).0,
+                   422);
+        let oversized = format!("POST /scores?name=mallory HTTP/1.1\r\nContent-Length: {}",
+                                MAX_BODY_LEN + 1);
+        assert_eq!(request(&addr, &oversized, "").0, 413);
+        assert_eq!(request(&addr, "POST /scores HTTP/1.1\r\nContent-Length: 100", "short").0,
+                   400);
+        assert_eq!(request(&addr, "garbage", "").0, 400);
+
+        assert_eq!(request(&addr, "GET /scores HTTP/1.1", ""),
+                   (200, format!("1 {} alice classic 1 1\n", score)));
+        assert_eq!(request(&addr, "GET /replays/1 HTTP/1.1", ""), (200, text));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn limits_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let dir = env::temp_dir()
+            .join(format!("catchit-leaderboard-{}", listener.local_addr().unwrap().port()));
+        let _ = fs::remove_dir_all(&dir);
+        let board = Leaderboard::open(&dir).unwrap();
+        thread::spawn(move || accept(listener, board));
+
+        // Clients which don't send their requests keep their connections busy
+        let idle: Vec<TcpStream> = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(&addr[..]).unwrap())
+            .collect();
+        // Turned away before sending anything, as the request isn't read
+        let mut response = String::new();
+        TcpStream::connect(&addr[..]).unwrap().read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503"), "{}", response);
+
+        for stream in &idle {
+            stream.shutdown(Shutdown::Write).unwrap();
+        }
+        for mut stream in idle {
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+        }
+        // Their connections are served once they do, which makes room again
+        // Their connections are served once they do, which makes room again. Until
+        // then, the server may reset connections it turned away before reading them.
+        let served = (0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            let mut stream = TcpStream::connect(&addr[..]).unwrap();
+            let mut response = String::new();
+            let _ = write!(stream, "GET /scores HTTP/1.1\r\n\r\n")
+                .and_then(|_| stream.read_to_string(&mut response));
+            response.starts_with("HTTP/1.1 200")
+        });
+        assert!(served);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
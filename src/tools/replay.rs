@@ -0,0 +1,169 @@
+//! Works with replays recorded by catchit, which are stored in `~/.catchit/replays`
+extern crate catchit;
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use catchit::{Replay, Animation, AnimationFormat, SoftwareRenderer, Renderer, Theme, Hud,
+              Scalar, verify_replay};
+
+const USAGE: &'static str = "Usage: catchit-replay export <replay> <output.gif|output.png> \
+                             [--width N] [--height N] [--fps N] [--theme NAME]\n       \
+                             catchit-replay verify <replay>";
+const DEFAULT_FPS: f64 = 25.0;
+/// GIF delays are in hundredths of a second, so more frames can't be shown
+const MAX_FPS: f64 = 100.0;
+/// The largest width and height animations can store
+const MAX_SIZE: u32 = 65535;
+/// How long the last frame is shown before the animation starts over, in seconds
+const HOLD_LAST_FRAME: f64 = 2.0;
+/// Height of the HUD text in logical points, the HUD being twice as high
+const TEXT_HEIGHT: Scalar = 10.0;
+
+struct ExportOptions {
+    width: Option<u32>,
+    height: Option<u32>,
+    fps: f64,
+    theme: Theme,
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    value.and_then(|v| v.parse().ok())
+        .ok_or_else(|| format!("{} needs a positive number", flag))
+}
+
+fn parse_export_options<I: Iterator<Item = String>>(mut args: I) -> Result<ExportOptions, String> {
+    let mut opts = ExportOptions {
+        width: None,
+        height: None,
+        fps: DEFAULT_FPS,
+        theme: Theme::classic(),
+    };
+    while let Some(flag) = args.next() {
+        match &flag[..] {
+            "--width" => opts.width = Some(try!(parse_value(&flag, args.next()))),
+            "--height" => opts.height = Some(try!(parse_value(&flag, args.next()))),
+            "--fps" => opts.fps = try!(parse_value(&flag, args.next())),
+            "--theme" => {
+                let name = try!(args.next().ok_or("--theme needs the name of a theme"));
+                opts.theme = try!(Theme::builtin()
+                    .into_iter()
+                    .find(|t| t.name.to_lowercase() == name.to_lowercase())
+                    .ok_or_else(|| {
+                        let names: Vec<String> =
+                            Theme::builtin().into_iter().map(|t| t.name).collect();
+                        format!("Unknown theme '{}', choose one of: {}", name, names.join(", "))
+                    }));
+            }
+            _ => return Err(format!("Unknown option '{}'", flag)),
+        }
+    }
+    let valid_size = |size: Option<u32>| size.map_or(true, |s| s > 0 && s <= MAX_SIZE);
+    if !valid_size(opts.width) || !valid_size(opts.height) {
+        return Err(format!("Sizes must be between 1 and {}", MAX_SIZE));
+    }
+    if opts.fps.is_nan() || opts.fps <= 0.0 || opts.fps > MAX_FPS {
+        return Err(format!("The frame rate must be larger than zero and at most {}", MAX_FPS));
+    }
+    Ok(opts)
+}
+
+fn read_replay(path: &Path) -> Result<Replay, String> {
+    let mut content = String::new();
+    try!(File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut content))
+        .map_err(|e| format!("Could not read '{}': {}", path.display(), e)));
+    Replay::parse(&content).map_err(|e| format!("Invalid replay '{}': {}", path.display(), e))
+}
+
+/// Plays the replay again and renders it as animation, one frame every `1 / fps` seconds
+fn export(replay: &Replay, format: AnimationFormat, opts: &ExportOptions) -> Vec<u8> {
+    let size = [replay.field[0], replay.field[1] + TEXT_HEIGHT * 2.0];
+    // Sizes which aren't given can't be larger than animations allow either
+    let fit = |v: Scalar| (v.round() as u32).max(1).min(MAX_SIZE);
+    let (width, height) = match (opts.width, opts.height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, fit(w as Scalar * size[1] / size[0])),
+        (None, Some(h)) => (fit(h as Scalar * size[0] / size[1]), h),
+        (None, None) => (fit(size[0]), fit(size[1])),
+    };
+    let mut r = SoftwareRenderer::new(width, height, size);
+    r.text_height = TEXT_HEIGHT;
+
+    // The HUD shows the final score as best score, which needs the whole game
+    let final_score = replay.simulate(|_, _| {}).map(|s| s.score).unwrap_or(0);
+    let hud = Hud {
+        size: size,
+        field: replay.field,
+        best_score: final_score,
+        tries: 1,
+        ghost_score: None,
+    };
+
+    let frame_time = 1.0 / opts.fps;
+    let mut anim = Animation::new(format, width, height);
+    let mut next_frame = 0.0;
+    let last = replay.simulate(|time, s| {
+        if time >= next_frame {
+            r.draw_scene(Some(s), &hud, &opts.theme);
+            anim.push(r.image(), frame_time);
+            while next_frame <= time {
+                next_frame += frame_time;
+            }
+        }
+    });
+    if let Some(s) = last {
+        r.draw_scene(Some(&s), &hud, &opts.theme);
+        anim.push(r.image(), HOLD_LAST_FRAME);
+    }
+    anim.finish()
+}
+
+fn run() -> Result<(), String> {
+    let mut args = env::args().skip(1);
+    match args.next().as_ref().map(|s| &s[..]) {
+        Some("export") => {
+            let (input, output) = match (args.next(), args.next()) {
+                (Some(i), Some(o)) => (i, o),
+                _ => return Err(USAGE.to_string()),
+            };
+            let opts = try!(parse_export_options(args));
+            let output = Path::new(&output);
+            let format = try!(output.extension()
+                .and_then(|e| e.to_str())
+                .and_then(AnimationFormat::from_extension)
+                .ok_or("The output file must end in .gif or .png"));
+            let replay = try!(read_replay(Path::new(&input)));
+            if replay.inputs.is_empty() {
+                return Err(format!("'{}' contains no input to play", input));
+            }
+            let data = export(&replay, format, &opts);
+            try!(File::create(output)
+                .and_then(|mut f| f.write_all(&data))
+                .map_err(|e| format!("Could not write '{}': {}", output.display(), e)));
+            println!("Exported {:.1}s of play to '{}'", replay.duration(), output.display());
+            Ok(())
+        }
+        Some("verify") => {
+            let input = try!(args.next().ok_or(USAGE));
+            let replay = try!(read_replay(Path::new(&input)));
+            let verified = try!(verify_replay(&replay)
+                .map_err(|e| format!("'{}' failed verification: {}", input, e)));
+            println!("Verified a score of {} after {} updates ({:.1}s)",
+                     verified.score,
+                     verified.ticks,
+                     verified.time);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        println!("catchit-replay: {}", err);
+        std::process::exit(1);
+    }
+}
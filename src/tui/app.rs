@@ -0,0 +1,268 @@
+use std::io::{self, Write, stdin, stdout};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use catchit::{Engine, State, Theme, Renderer, SoftwareRenderer, Extent, Position, Scalar};
+
+use termion::event::{Event, Key, MouseEvent, MouseButton};
+use termion::input::{TermRead, MouseTerminal};
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use termion::{self, clear, cursor};
+
+use term;
+
+/// The playing field has the same size as in the window, and is scaled to the terminal
+const FIELD: Extent = [800.0, 560.0];
+const FRAMES_PER_SECOND: u64 = 30;
+/// Logical points the hunter moves per key press
+const KEY_STEP: Scalar = 20.0;
+/// Rows below the playing field, for the HUD and the help line
+const HUD_ROWS: u16 = 2;
+/// Makes the terminal report mouse motion even if no button is pressed
+const ENTER_ANY_MOTION: &'static str = "\x1b[?1003h";
+const EXIT_ANY_MOTION: &'static str = "\x1b[?1003l";
+
+enum Mode {
+    /// Showing a fresh game, waiting for the player to start it
+    Ready,
+    Playing,
+    Paused,
+    /// Showing the last state of the finished game
+    GameOver(State),
+}
+
+struct App {
+    engine: Engine,
+    mode: Mode,
+    themes: Vec<Theme>,
+    /// Index into `themes`
+    theme: usize,
+    /// Where the hunter is moved to with the next update, in logical points
+    target: Position,
+    force: bool,
+    best_score: u32,
+    tries: u32,
+    /// Terminal size in columns and rows
+    size: (u16, u16),
+    renderer: SoftwareRenderer,
+    quit: bool,
+}
+
+fn field_renderer(size: (u16, u16)) -> SoftwareRenderer {
+    let rows = size.1.saturating_sub(HUD_ROWS).max(1);
+    SoftwareRenderer::new(size.0.max(1) as u32, rows as u32 * 2, FIELD)
+}
+
+fn as_secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
+
+impl App {
+    fn new(size: (u16, u16)) -> App {
+        let engine = Engine::from_field(FIELD);
+        let target = engine.state().as_ref().map(|s| s.hunter.object.pos).unwrap_or([0.0, 0.0]);
+        App {
+            engine: engine,
+            mode: Mode::Ready,
+            themes: Theme::builtin(),
+            theme: 0,
+            target: target,
+            force: false,
+            best_score: 0,
+            tries: 0,
+            size: size,
+            renderer: field_renderer(size),
+            quit: false,
+        }
+    }
+
+    fn resize(&mut self, size: (u16, u16)) {
+        self.size = size;
+        self.renderer = field_renderer(size);
+    }
+
+    fn new_game(&mut self) {
+        self.engine.reset(FIELD);
+        self.force = false;
+        self.mode = Mode::Playing;
+    }
+
+    fn set_force(&mut self, enabled: bool) {
+        if let Mode::Playing = self.mode {
+            self.force = enabled;
+            self.engine.set_hunter_force(enabled);
+        }
+    }
+
+    /// Aim the hunter at the center of the given cell, with (1, 1) being the top-left one
+    fn move_to_cell(&mut self, x: u16, y: u16) {
+        let pixel = [x as Scalar - 0.5, (y as Scalar - 1.0) * 2.0 + 1.0];
+        self.target = self.renderer.to_logical(pixel);
+    }
+
+    fn move_by(&mut self, dx: Scalar, dy: Scalar) {
+        self.target[0] = (self.target[0] + dx).max(0.0).min(FIELD[0]);
+        self.target[1] = (self.target[1] + dy).max(0.0).min(FIELD[1]);
+    }
+
+    /// Space starts, continues and restarts the game, and toggles the force while playing.
+    /// Terminals don't report key releases, which is why the force can't be held.
+    fn on_space(&mut self) {
+        match self.mode {
+            Mode::Ready | Mode::Paused => self.mode = Mode::Playing,
+            Mode::GameOver(_) => self.new_game(),
+            Mode::Playing => {
+                let force = !self.force;
+                self.set_force(force);
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: Event) {
+        match event {
+            Event::Key(Key::Char('q')) |
+            Event::Key(Key::Esc) |
+            Event::Key(Key::Ctrl('c')) => self.quit = true,
+            Event::Key(Key::Char(' ')) |
+            Event::Key(Key::Char('\n')) |
+            Event::Key(Key::Char('f')) => self.on_space(),
+            Event::Key(Key::Char('p')) => {
+                self.mode = match self.mode {
+                    Mode::Playing => {
+                        self.set_force(false);
+                        Mode::Paused
+                    }
+                    Mode::Paused => Mode::Playing,
+                    _ => return,
+                }
+            }
+            Event::Key(Key::Char('t')) => self.theme = (self.theme + 1) % self.themes.len(),
+            Event::Key(Key::Left) | Event::Key(Key::Char('h')) => self.move_by(-KEY_STEP, 0.0),
+            Event::Key(Key::Right) | Event::Key(Key::Char('l')) => self.move_by(KEY_STEP, 0.0),
+            Event::Key(Key::Up) | Event::Key(Key::Char('k')) => self.move_by(0.0, -KEY_STEP),
+            Event::Key(Key::Down) | Event::Key(Key::Char('j')) => self.move_by(0.0, KEY_STEP),
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => {
+                self.move_to_cell(x, y);
+                match self.mode {
+                    Mode::Playing => self.set_force(true),
+                    _ => self.on_space(),
+                }
+            }
+            Event::Mouse(MouseEvent::Release(x, y)) => {
+                self.move_to_cell(x, y);
+                self.set_force(false);
+            }
+            Event::Mouse(MouseEvent::Hold(x, y)) => self.move_to_cell(x, y),
+            Event::Unsupported(ref seq) => {
+                if let Some((x, y)) = term::parse_motion(seq) {
+                    self.move_to_cell(x, y);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, dt: f64) {
+        if let Mode::Playing = self.mode {
+            self.engine.set_hunter_pos(self.target);
+            if let Err(state) = self.engine.update(dt) {
+                self.tries += 1;
+                self.best_score = self.best_score.max(state.score);
+                self.force = false;
+                self.mode = Mode::GameOver(state);
+            }
+        }
+    }
+
+    /// Appends the escape sequences drawing the whole terminal to `out`
+    fn draw(&mut self, out: &mut String) {
+        let theme = &self.themes[self.theme];
+        let state = match self.mode {
+            Mode::GameOver(ref s) => Some(s),
+            _ => self.engine.state().as_ref(),
+        };
+
+        self.renderer.clear(theme.background);
+        if let Some(s) = state {
+            self.renderer.draw_state(s, theme);
+        }
+        term::draw_image(out, self.renderer.image());
+
+        let mut hud = format!(" Best Score: {}   Tries: {}", self.best_score, self.tries);
+        if let Some(s) = state {
+            hud.push_str(&format!("   Score: {}   Multiplier: {:.2}", s.score, s.score_coeff));
+            if s.combo > 1 {
+                hud.push_str(&format!("   Combo x{} ({:.1}s)", s.combo, s.combo_time_left));
+            }
+        }
+        let help = match self.mode {
+            Mode::Ready => {
+                " Space or click: start   mouse or arrows: move   t: theme   q: quit".to_string()
+            }
+            Mode::Playing => {
+                format!(" Force: {} (click or Space)   p: pause   q: quit",
+                        if self.force { "on" } else { "off" })
+            }
+            Mode::Paused => " Paused - press p or Space to continue".to_string(),
+            Mode::GameOver(_) => " Game Over - press Space for a new game, q to quit".to_string(),
+        };
+        let rows = self.size.1.saturating_sub(HUD_ROWS);
+        term::draw_line(out, rows + 1, self.size.0, &hud, theme.foreground, theme.background);
+        term::draw_line(out, rows + 2, self.size.0, &help, theme.background, theme.foreground);
+    }
+}
+
+pub fn run() -> io::Result<()> {
+    let mut size = try!(termion::terminal_size());
+    let raw = try!(stdout().into_raw_mode());
+    let mut out = AlternateScreen::from(MouseTerminal::from(raw));
+    try!(write!(out, "{}{}{}", cursor::Hide, clear::All, ENTER_ANY_MOTION));
+
+    // Reading blocks, so it happens on its own thread
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let stdin = stdin();
+        for event in stdin.events() {
+            if let Ok(event) = event {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    let mut app = App::new(size);
+    let frame_time = Duration::from_millis(1000 / FRAMES_PER_SECOND);
+    let mut last_frame = Instant::now();
+    while !app.quit {
+        let now = Instant::now();
+        let dt = as_secs(now.duration_since(last_frame));
+        last_frame = now;
+
+        while let Ok(event) = rx.try_recv() {
+            app.on_event(event);
+        }
+        app.update(dt);
+
+        let new_size = try!(termion::terminal_size());
+        if new_size != size {
+            size = new_size;
+            app.resize(size);
+            try!(write!(out, "{}", clear::All));
+        }
+        let mut frame = String::new();
+        app.draw(&mut frame);
+        try!(out.write_all(frame.as_bytes()));
+        try!(out.flush());
+
+        let elapsed = now.elapsed();
+        if elapsed < frame_time {
+            thread::sleep(frame_time - elapsed);
+        }
+    }
+
+    try!(write!(out, "{}{}", EXIT_ANY_MOTION, cursor::Show));
+    out.flush()
+}
@@ -0,0 +1,22 @@
+//! A terminal frontend for catchit, which can be played over SSH
+#[cfg(unix)]
+extern crate termion;
+extern crate catchit;
+
+#[cfg(unix)]
+mod app;
+#[cfg(unix)]
+mod term;
+
+#[cfg(unix)]
+fn main() {
+    if let Err(err) = app::run() {
+        println!("catchit-tui: {}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("catchit-tui needs a unix terminal - please use catchit instead");
+}
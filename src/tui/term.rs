@@ -0,0 +1,70 @@
+use std::fmt::Write;
+
+use catchit::{Image, Color};
+
+use termion::{color, cursor, style};
+
+/// Upper half block: the top pixel is the foreground, the bottom one the background
+const HALF_BLOCK: char = '\u{2580}';
+
+fn rgb(c: [u8; 4]) -> color::Rgb {
+    color::Rgb(c[0], c[1], c[2])
+}
+
+pub fn to_rgb(c: Color) -> color::Rgb {
+    let b = |v: f32| (v.max(0.0).min(1.0) * 255.0).round() as u8;
+    color::Rgb(b(c[0]), b(c[1]), b(c[2]))
+}
+
+/// Appends escape sequences drawing `img` to `out`, starting at the top-left corner.
+///
+/// Each character cell shows two pixels stacked on top of each other, which are about
+/// square on most terminals. The image height should thus be even.
+pub fn draw_image(out: &mut String, img: &Image) {
+    let mut last = None;
+    for row in 0..img.height / 2 {
+        write!(out, "{}", cursor::Goto(1, row as u16 + 1)).unwrap();
+        for x in 0..img.width {
+            let colors = (img.pixel(x, row * 2), img.pixel(x, row * 2 + 1));
+            // Only emit colours when they change, to keep the output small
+            if last != Some(colors) {
+                write!(out, "{}{}", color::Fg(rgb(colors.0)), color::Bg(rgb(colors.1)))
+                    .unwrap();
+                last = Some(colors);
+            }
+            out.push(HALF_BLOCK);
+        }
+    }
+    write!(out, "{}", style::Reset).unwrap();
+}
+
+/// Appends `text` as one line at the given terminal row, filling it up to `width`
+pub fn draw_line(out: &mut String, row: u16, width: u16, text: &str, fg: Color, bg: Color) {
+    let text: String = text.chars().take(width as usize).collect();
+    write!(out,
+           "{}{}{}{:<width$}{}",
+           cursor::Goto(1, row),
+           color::Fg(to_rgb(fg)),
+           color::Bg(to_rgb(bg)),
+           text,
+           style::Reset,
+           width = width as usize)
+        .unwrap();
+}
+
+/// Extracts the position from mouse motion reported while no button is pressed,
+/// like `ESC [ < 35 ; x ; y M`, which termion does not know about
+pub fn parse_motion(seq: &[u8]) -> Option<(u16, u16)> {
+    let seq = match ::std::str::from_utf8(seq) {
+        Ok(seq) => seq,
+        Err(_) => return None,
+    };
+    if !seq.starts_with("\x1b[<35;") || !seq.ends_with('M') {
+        return None;
+    }
+    let mut nums = seq["\x1b[<35;".len()..seq.len() - 1].split(';').map(|n| n.parse::<u16>());
+    match (nums.next(), nums.next()) {
+        (Some(Ok(x)), Some(Ok(y))) => Some((x, y)),
+        _ => None,
+    }
+}
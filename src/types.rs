@@ -9,6 +9,7 @@ pub type Position = vecmath::Vector2<Scalar>;
 pub type Velocity = vecmath::Vector2<Scalar>;
 
 use transition::Transition;
+use level::WinCondition;
 
 /// Points on screen. Usually they correspond to pixels, but might not on a
 /// `HiDPI` display
@@ -70,12 +71,300 @@ pub enum ObstacleKind {
     Deadly,
 }
 
+impl ObstacleKind {
+    /// The name used in files
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ObstacleKind::AttractiveForceSwitch => "attractive_force_switch",
+            ObstacleKind::InvisibiltySwitch => "invisibility_switch",
+            ObstacleKind::Deadly => "deadly",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ObstacleKind> {
+        [ObstacleKind::AttractiveForceSwitch, ObstacleKind::InvisibiltySwitch, ObstacleKind::Deadly]
+            .iter()
+            .cloned()
+            .find(|k| k.name() == name)
+    }
+}
+
+/// Seconds a game of `GameMode::TimeAttack` lasts
+const TIME_ATTACK_DURATION: f64 = 60.0;
+/// Seconds within which the next prey has to be caught to continue a combo
+pub const COMBO_WINDOW: f64 = 3.0;
+
+/// The rules a game is played by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// Catch prey until a deadly obstacle touches the hunter
+    Classic,
+    /// Catch as much prey as possible within a minute
+    TimeAttack,
+    /// Relaxed practice without deadly obstacles, which never ends
+    Zen,
+    /// Score by staying alive while obstacles keep coming, catching prey is optional
+    Survival,
+}
+
+const GAME_MODES: &'static [GameMode] = &[GameMode::Classic,
+                                          GameMode::TimeAttack,
+                                          GameMode::Zen,
+                                          GameMode::Survival];
+
+impl GameMode {
+    pub fn all() -> &'static [GameMode] {
+        GAME_MODES
+    }
+
+    /// The name used in files
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GameMode::Classic => "classic",
+            GameMode::TimeAttack => "time_attack",
+            GameMode::Zen => "zen",
+            GameMode::Survival => "survival",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        GAME_MODES.iter().cloned().find(|m| m.name() == name)
+    }
+
+    /// Human readable text, as displayed on screen
+    pub fn description(&self) -> &'static str {
+        match *self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Zen => "Zen",
+            GameMode::Survival => "Survival",
+        }
+    }
+
+    /// Seconds after which the game ends, if it is limited in time
+    pub fn time_limit(&self) -> Option<f64> {
+        match *self {
+            GameMode::TimeAttack => Some(TIME_ATTACK_DURATION),
+            _ => None,
+        }
+    }
+}
+
+impl Default for GameMode {
+    fn default() -> GameMode {
+        GameMode::Classic
+    }
+}
+
+/// Seconds it takes obstacles with a lifetime to fade in and out
+pub const OBSTACLE_FADE_DURATION: f64 = 0.5;
+
 /// An obstacle the hunter can collide with
 #[derive(Debug, Clone, PartialEq)]
 pub struct Obstacle {
     pub kind: ObstacleKind,
     pub object: Object,
     pub velocity: Velocity,
+    /// Seconds since the obstacle appeared
+    pub age: f64,
+    /// Seconds after which the obstacle disappears, or None if it stays forever
+    pub lifetime: Option<f64>,
+    /// Divides the forces acting on the obstacle
+    pub mass: Scalar,
+    /// The speed drag slows the obstacle down to, usually the one it started with
+    pub base_speed: Scalar,
+    /// True once the obstacle was told to `leave()`, even if it still fades in
+    pub departing: bool,
+}
+
+/// Seconds an obstacle with the given lifetime takes to fade in and out
+fn fade_duration(lifetime: f64) -> f64 {
+    OBSTACLE_FADE_DURATION.min(lifetime / 2.0)
+}
+
+impl Obstacle {
+    /// How visible the obstacle is, from 0 to 1. Obstacles with a lifetime fade in
+    /// after appearing and fade out before they disappear, and stop colliding with
+    /// the hunter once they fade out.
+    pub fn opacity(&self) -> Scalar {
+        match self.lifetime {
+            Some(lifetime) => {
+                (self.age.min(lifetime - self.age) / fade_duration(lifetime)).max(0.0).min(1.0)
+            }
+            None => 1.0,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.lifetime.map_or(false, |lifetime| self.age >= lifetime)
+    }
+
+    /// True once the obstacle started to fade out
+    pub fn is_leaving(&self) -> bool {
+        self.lifetime.map_or(false, |lifetime| lifetime - self.age <= fade_duration(lifetime))
+    }
+
+    /// True once the obstacle was told to leave or started to fade out. Such obstacles
+    /// don't count towards `ObstacleLimits::max_count`.
+    pub fn is_departing(&self) -> bool {
+        self.departing || self.is_leaving()
+    }
+
+    /// Let the obstacle fade out and disappear, unless it is already leaving.
+    /// Obstacles which are still fading in finish doing so first.
+    pub fn leave(&mut self) {
+        self.departing = true;
+        if !self.is_leaving() {
+            let lifetime = (self.age + OBSTACLE_FADE_DURATION).max(2.0 * OBSTACLE_FADE_DURATION);
+            self.lifetime = Some(lifetime);
+        }
+    }
+}
+
+/// How obstacles react to the forces of the hunter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Physics {
+    /// How quickly obstacles lose speed exceeding their `base_speed`, per second
+    pub drag: Scalar,
+    /// The fastest obstacles may move, in lengths of the shorter side of the field
+    /// per second
+    pub max_speed: Scalar,
+}
+
+impl Default for Physics {
+    fn default() -> Physics {
+        Physics {
+            drag: 1.0,
+            max_speed: 1.5,
+        }
+    }
+}
+
+/// Which obstacles make room for new ones once there are too many
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecycleStrategy {
+    /// The ones which appeared first
+    Oldest,
+    /// The ones farthest from the hunter
+    Farthest,
+}
+
+/// Restrictions for the obstacles of a game, which don't apply by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObstacleLimits {
+    /// Seconds new obstacles stay, unless they define their own lifetime
+    pub lifetime: Option<f64>,
+    /// The most obstacles in the field at once, not counting those fading out
+    pub max_count: Option<usize>,
+    pub recycle: RecycleStrategy,
+}
+
+impl Default for ObstacleLimits {
+    fn default() -> ObstacleLimits {
+        ObstacleLimits {
+            lifetime: None,
+            max_count: None,
+            recycle: RecycleStrategy::Oldest,
+        }
+    }
+}
+
+/// Something noteworthy which happened during an update of the `Engine`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The hunter caught the prey at `pos`, and earned `score` points for it
+    PreyCaught { pos: Position, score: u32 },
+    /// The hunter hit a special obstacle at `pos`, activating its effect
+    SwitchHit { kind: ObstacleKind, pos: Position },
+    /// The hunter hit a deadly obstacle at `pos`
+    GameOver { pos: Position },
+    /// The game ended as its time ran out
+    TimeUp,
+    /// The goal of the given wave of the given level was reached
+    WaveCleared { level: usize, wave: usize },
+    /// The last wave of the last level was cleared, which ends the game
+    CampaignComplete,
+    /// An obstacle at `pos` disappeared as its lifetime ended, or to make room for
+    /// a new one
+    ObstacleDespawned { kind: ObstacleKind, pos: Position },
+}
+
+/// Progress through the current wave of a game played from `Level`s
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveStatus {
+    /// Index of the level being played
+    pub level: usize,
+    /// Index of the wave within its level
+    pub wave: usize,
+    pub win: WinCondition,
+    /// Prey caught during this wave
+    pub caught: u32,
+    /// Seconds passed since the wave started
+    pub time: f64,
+}
+
+impl WaveStatus {
+    pub fn is_won(&self) -> bool {
+        match self.win {
+            WinCondition::CatchPrey(count) => self.caught >= count,
+            WinCondition::Survive(seconds) => self.time >= seconds,
+        }
+    }
+}
+
+/// What points were awarded for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreSource {
+    /// Catching prey
+    Prey,
+    /// Staying alive in `GameMode::Survival`
+    Survival,
+}
+
+/// Points awarded at one moment of a game, along with how they were calculated
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreEntry {
+    /// Seconds into the game
+    pub time: f64,
+    pub source: ScoreSource,
+    /// Points before any multiplier
+    pub base: Scalar,
+    /// The `State::score_coeff` at the time
+    pub score_coeff: Scalar,
+    /// The multiplier of the combo at the time
+    pub combo: u32,
+    /// The product of the multipliers of active special obstacle states
+    pub special: Scalar,
+    /// Points added to the score
+    pub points: u32,
+}
+
+impl ScoreEntry {
+    /// Awards the base points times all multipliers, rounded down
+    pub fn new(time: f64,
+               source: ScoreSource,
+               base: Scalar,
+               score_coeff: Scalar,
+               combo: u32,
+               special: Scalar)
+               -> ScoreEntry {
+        let mut entry = ScoreEntry {
+            time: time,
+            source: source,
+            base: base,
+            score_coeff: score_coeff,
+            combo: combo,
+            special: special,
+            points: 0,
+        };
+        entry.points = (base * entry.multiplier()) as u32;
+        entry
+    }
+
+    /// The product of all multipliers
+    pub fn multiplier(&self) -> Scalar {
+        self.score_coeff * self.combo as Scalar * self.special
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -83,6 +372,117 @@ pub struct Hunter {
     pub object: Object,
     pub force: Scalar,
     pub velocity: Velocity,
+    /// Where the player wants the hunter to be
+    pub target: Position,
+}
+
+impl Hunter {
+    /// The distance up to which the hunter's force acts on obstacles
+    pub fn force_radius(&self) -> Scalar {
+        self.object.half_size * 2.0 * 4.0
+    }
+}
+
+/// A damped spring pulling the hunter towards its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    /// Acceleration per unit of distance to the target, per second squared
+    pub stiffness: Scalar,
+    /// How much of its velocity the hunter loses, per second
+    pub damping: Scalar,
+    /// The fastest the hunter may move, in lengths of the shorter side of the field
+    /// per second
+    pub max_speed: Scalar,
+}
+
+impl Default for Spring {
+    fn default() -> Spring {
+        Spring {
+            stiffness: 60.0,
+            damping: 11.0,
+            max_speed: 2.0,
+        }
+    }
+}
+
+/// How the hunter follows the position given by the player
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Movement {
+    /// The hunter is exactly where the player points
+    Direct,
+    /// The hunter keeps its momentum while being pulled towards where the player points
+    Inertial(Spring),
+}
+
+impl Movement {
+    /// The name used in files
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Movement::Direct => "direct",
+            Movement::Inertial(_) => "inertial",
+        }
+    }
+
+    /// The movement of the given name, with default settings
+    pub fn from_name(name: &str) -> Option<Movement> {
+        match name {
+            "direct" => Some(Movement::Direct),
+            "inertial" => Some(Movement::Inertial(Spring::default())),
+            _ => None,
+        }
+    }
+
+    /// The name followed by the settings, as written in files. Numbers are written
+    /// such that they parse back to exactly the same value.
+    pub fn to_text(&self) -> String {
+        match *self {
+            Movement::Direct => self.name().to_string(),
+            Movement::Inertial(ref spring) => {
+                format!("{} {:?} {:?} {:?}",
+                        self.name(),
+                        spring.stiffness,
+                        spring.damping,
+                        spring.max_speed)
+            }
+        }
+    }
+
+    /// Parses what `to_text` returns. The settings of a spring have to be positive.
+    pub fn parse(text: &str) -> Option<Movement> {
+        let values: Vec<&str> = text.split_whitespace().collect();
+        match (values.first().cloned(), values.len()) {
+            (Some("direct"), 1) => Some(Movement::Direct),
+            (Some("inertial"), 4) => {
+                let v: Vec<Scalar> = match values[1..].iter().map(|v| v.parse()).collect() {
+                    Ok(v) => v,
+                    Err(_) => return None,
+                };
+                if !v.iter().all(|v| v.is_finite() && *v > 0.0) {
+                    return None;
+                }
+                Some(Movement::Inertial(Spring {
+                    stiffness: v[0],
+                    damping: v[1],
+                    max_speed: v[2],
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Human readable text, as displayed on screen
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Movement::Direct => "Direct",
+            Movement::Inertial(_) => "Inertial",
+        }
+    }
+}
+
+impl Default for Movement {
+    fn default() -> Movement {
+        Movement::Direct
+    }
 }
 
 /// It maintains the state of the game and expects to be updated with
@@ -92,6 +492,8 @@ pub struct Hunter {
 /// and grow
 #[derive(Debug, Clone, PartialEq)]
 pub struct State {
+    /// The rules of this game
+    pub mode: GameMode,
     /// The playing field
     pub field: Extent,
     /// The player's character
@@ -110,4 +512,14 @@ pub struct State {
     pub attracting_force: Transition,
     /// Last delta-time during update
     pub last_dt: f64,
+    /// Seconds the game is running
+    pub time: f64,
+    /// Set if the game is played from levels
+    pub wave: Option<WaveStatus>,
+    /// Prey caught in a row, each within `COMBO_WINDOW` seconds of the previous one
+    pub combo: u32,
+    /// Seconds left to catch the next prey before the combo breaks
+    pub combo_time_left: f64,
+    /// How the score came about, with one entry for each time points were awarded
+    pub ledger: Vec<ScoreEntry>,
 }